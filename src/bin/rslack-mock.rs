@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+use std::process;
+
+use anyhow::Result;
+use clap::Parser;
+use tokio::net::TcpListener;
+
+use rslack::mock::{self, Workspace};
+
+/// Serve a mock Slack Web API from an in-memory workspace
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Opt {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Port to listen on
+    #[arg(short, long, default_value_t = 8787)]
+    port: u16,
+
+    /// JSON file with the channels, users, emoji and messages to start with
+    /// (default: a small demo workspace)
+    #[arg(short, long)]
+    workspace: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(err) = run().await {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+async fn run() -> Result<()> {
+    let opts = Opt::parse();
+    let workspace = match &opts.workspace {
+        Some(path) => Workspace::load(path)?,
+        None => Workspace::demo(),
+    };
+
+    let listener = TcpListener::bind((opts.host.as_str(), opts.port)).await?;
+    println!(
+        "Serving a mock Slack workspace at http://{}",
+        listener.local_addr()?
+    );
+    mock::serve(listener, workspace.into_fake()).await
+}
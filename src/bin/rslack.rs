@@ -1,75 +1,630 @@
-use std::io::{stdin, stdout};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{stdin, stdout, BufWriter, Write};
+use std::process;
 
 use anyhow::{anyhow, Result};
+use chrono::{Local, LocalResult, TimeZone};
 use termion::raw::IntoRawMode;
 use termion::screen::IntoAlternateScreen;
 
-use rslack::config::{Config, SLACK_URL};
-use rslack::console::{print_messages, ChannelSelector, Editor, EditorResult, SelectionResult};
-use rslack::option::{Opt, DEFAULT_MESSAGE_LIMIT};
-use rslack::slack;
+use rslack::config::Config;
+use rslack::console::{
+    external_editor, print_messages, print_search_results, prompt, App, ChannelSelector, Completer,
+    Editor, EditorResult, SelectionResult,
+};
+use rslack::draft::DraftStore;
+use rslack::export::Transcript;
+use rslack::option::{
+    Command, DropArgs, ExportArgs, Opt, OutboxArgs, OutboxCommand, PostArgs, SearchArgs,
+    DEFAULT_MESSAGE_LIMIT, DEFAULT_SEARCH_LIMIT,
+};
+use rslack::outbox::{Outbox, QueuedMessage};
+use rslack::sent::SentHistory;
+use rslack::slack::{
+    is_transient, split_message, ChannelRef, SearchQuery, SlackApi, SlackChannel, SlackChannels,
+    SlackClient, MAX_CONCURRENT_POSTS, MAX_MESSAGE_LENGTH,
+};
+use rslack::template::Template;
+use rslack::timerange;
 
 #[tokio::main]
 async fn main() {
     if let Err(err) = run().await {
         eprintln!("{}", err);
+        process::exit(1);
     }
 }
 
 async fn run() -> Result<()> {
-    let opts = Opt::get_opts();
-    let mut channel = opts.channel.unwrap_or_default();
-    let mut message = opts.message.unwrap_or_default();
-    let read_mode = opts.read;
-    let limit = opts.limit.unwrap_or(DEFAULT_MESSAGE_LIMIT);
-
+    let mut opts = Opt::get_opts();
     let config = Config::new(None)?;
-    let slack_client = slack::SlackClient::new(&config, SLACK_URL);
-    let channels = slack_client.get_channels().await?;
+    let slack_client = SlackClient::new(&config, config.base_url())?;
+
+    match opts.command.take() {
+        Some(Command::Search(args)) => search(&config, &slack_client, args).await,
+        Some(Command::Export(args)) => export(&config, &slack_client, args).await,
+        Some(Command::Post(args)) => post_template(opts, &config, &slack_client, args).await,
+        Some(Command::Outbox(args)) => outbox(&slack_client, args).await,
+        Some(Command::Tui) => tui(opts, &config, slack_client).await,
+        None => post_or_read(opts, &config, &slack_client, None).await,
+    }
+}
+
+/*
+ * Render a template from the config file, asking for variables that were not
+ * given with --var, and open the result in the editor for final changes.
+ */
+async fn post_template(
+    mut opts: Opt,
+    config: &Config,
+    slack_client: &impl SlackApi,
+    args: PostArgs,
+) -> Result<()> {
+    let template = config
+        .template(&args.template)
+        .map(Template::new)
+        .ok_or_else(|| anyhow!("Template '{}' not found", args.template))?;
+
+    let mut vars: BTreeMap<String, String> = args.vars.into_iter().collect();
+    for name in template.variables() {
+        if let Entry::Vacant(entry) = vars.entry(name) {
+            let value = prompt::ask(stdin().lock(), &mut stdout(), entry.key())?;
+            entry.insert(value);
+        }
+    }
+    let text = template.render(&vars)?;
+
+    opts.channel = args.channel;
+    opts.message = None;
+    opts.editor = false;
+    opts.read = false;
+    opts.yes = args.yes;
+    opts.dry_run = args.dry_run;
+    post_or_read(opts, config, slack_client, Some(text)).await
+}
+
+async fn search(config: &Config, slack_client: &impl SlackApi, args: SearchArgs) -> Result<()> {
+    let query = SearchQuery {
+        in_channel: args
+            .in_channel
+            .map(|channel| config.resolve_channel(channel.trim_start_matches('#'))),
+        from: args.from,
+        after: args.after,
+        query: args.query,
+    };
+    let limit = args.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+
+    let matches = slack_client.search_messages(&query, limit).await?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&matches)?);
+    } else {
+        print_search_results(&query.to_query_string(), &matches);
+    }
+
+    Ok(())
+}
+
+async fn export(config: &Config, slack_client: &impl SlackApi, args: ExportArgs) -> Result<()> {
+    let range = timerange::history_range(args.since.as_deref(), args.until.as_deref(), false)?;
+
+    let reference = channel_ref(config, &args.channel);
+    let slack_channels = slack_client.get_channels().await?;
+    let channel = slack_client
+        .resolve_channel(&slack_channels, &reference)
+        .await?
+        .ok_or_else(|| anyhow!("Channel '{}' not found", args.channel))?;
+
+    let messages = slack_client.get_history(&channel.id, &range).await?;
+
+    let mut replies = HashMap::new();
+    if args.threads {
+        for message in messages.messages.iter().filter(|m| m.has_replies()) {
+            let thread = slack_client.get_replies(&channel.id, &message.ts).await?;
+            replies.insert(message.ts.clone(), thread.messages);
+        }
+    }
+
+    let users = slack_client.get_users().await.unwrap_or_else(|err| {
+        eprintln!("[Warning] Could not resolve user names: {}", err);
+        Vec::new().into()
+    });
+
+    let transcript = Transcript::new(&channel.name, &messages.messages, &replies, &users);
+    match &args.output {
+        Some(path) => {
+            let mut file = BufWriter::new(File::create(path)?);
+            transcript.write(&mut file, args.format)?;
+            file.flush()?;
+            println!(
+                "[Success] Exported {} messages from #{} to {}",
+                transcript.messages.len(),
+                channel.name,
+                path.display()
+            );
+        }
+        None => transcript.write(&mut stdout().lock(), args.format)?,
+    }
 
-    let channel_names = channels.channel_names();
-    let max_col_size = channels.max_channel_size() + 1;
-    let selector = ChannelSelector::new(channel_names.clone(), max_col_size);
+    Ok(())
+}
+
+async fn outbox(slack_client: &impl SlackApi, args: OutboxArgs) -> Result<()> {
+    let mut outbox = Outbox::open_default()?;
+
+    match args.command {
+        OutboxCommand::List => {
+            if outbox.is_empty() {
+                println!("The outbox is empty");
+            }
+            for (index, message) in outbox.entries().iter().enumerate() {
+                let thread = match &message.thread_ts {
+                    Some(ts) => format!(" (thread {})", ts),
+                    None => String::new(),
+                };
+                println!(
+                    "[{}] #{} {}{}",
+                    index + 1,
+                    message.channel_name,
+                    queued_time(message),
+                    thread
+                );
+                println!(" {}", message.text);
+            }
+        }
+        OutboxCommand::Flush => {
+            let results = outbox.flush(slack_client).await?;
+            print_flushed(&results);
+            if !outbox.is_empty() {
+                anyhow::bail!("{} posts are left in the outbox", outbox.entries().len());
+            }
+        }
+        OutboxCommand::Drop(DropArgs { all: true, .. }) => {
+            let count = outbox.entries().len();
+            outbox.clear()?;
+            println!("Dropped {} posts", count);
+        }
+        OutboxCommand::Drop(DropArgs { number, .. }) => {
+            let number = number.unwrap_or_default();
+            let dropped = outbox.remove(number.saturating_sub(1))?;
+            println!("Dropped [{}] #{}", number, dropped.channel_name);
+        }
+    }
+
+    Ok(())
+}
+
+/*
+ * Post messages queued while Slack could not be reached, oldest first.
+ * The outbox only keeps messages from getting lost, so problems with it are warnings.
+ */
+async fn flush_outbox(slack_client: &impl SlackApi) {
+    let mut outbox = match Outbox::open_default() {
+        Ok(outbox) if !outbox.is_empty() => outbox,
+        _ => return,
+    };
+    match outbox.flush(slack_client).await {
+        Ok(results) => print_flushed(&results),
+        Err(err) => eprintln!("[Warning] Could not update the outbox: {}", err),
+    }
+}
+
+fn print_flushed(results: &[(QueuedMessage, Result<()>)]) {
+    for (message, result) in results {
+        match result {
+            Ok(()) => println!(
+                "[Success] #{} (queued {})",
+                message.channel_name,
+                queued_time(message)
+            ),
+            Err(err) => eprintln!("[Queued] #{}: {}", message.channel_name, err),
+        }
+    }
+}
+
+fn queued_time(message: &QueuedMessage) -> String {
+    match Local.timestamp_opt(message.queued_at, 0) {
+        LocalResult::Single(dt) => dt.format("%Y-%m-%d %H:%M").to_string(),
+        _ => message.queued_at.to_string(),
+    }
+}
+
+async fn tui(opts: Opt, config: &Config, slack_client: impl SlackApi) -> Result<()> {
+    let slack_channels = slack_client.get_channels().await?;
+
+    let completer = completer(&slack_client, &slack_channels).await;
+    let mut app = App::new(slack_channels.channels.clone())
+        .with_completer(completer)
+        .with_post_checks(config);
+    if let Ok(store) = DraftStore::open_default() {
+        app = app.with_drafts(store);
+    }
+    if let Ok(outbox) = Outbox::open_default() {
+        app = app.with_outbox(outbox);
+    }
+    // The default channel may be configured as a name, an ID or an alias like --channel
+    let input = opts
+        .channel
+        .first()
+        .map(String::as_str)
+        .or(config.default_channel());
+    if let Some(input) = input {
+        let reference = channel_ref(config, input);
+        match slack_channels.find(&reference) {
+            Some(channel) => app.select(&channel.name),
+            None => app.set_status(&format!("Channel '{}' not found", input)),
+        }
+    }
 
     let stdout = stdout().into_raw_mode()?;
     let mut stdout = stdout.into_alternate_screen()?;
 
-    if selector.needs_selection(&channel) {
-        match selector.run(stdin(), &mut stdout)? {
-            SelectionResult::Selected(selected) => channel = selected,
-            SelectionResult::Cancelled => return Ok(()),
+    app.run(slack_client, stdin(), &mut stdout, || {
+        termion::terminal_size().unwrap_or((100, 100))
+    })
+    .await
+}
+
+/*
+ * Build editor completion from workspace users, channels and custom emoji.
+ * Completion is a convenience, so users and emoji that cannot be fetched are left out.
+ */
+async fn completer(slack_client: &impl SlackApi, slack_channels: &SlackChannels) -> Completer {
+    let (users, emoji) = tokio::join!(slack_client.get_users(), slack_client.get_emoji());
+    Completer::new(
+        &users.unwrap_or_else(|_| Vec::new().into()),
+        slack_channels,
+        emoji.unwrap_or_default(),
+    )
+}
+
+/*
+ * Encode mentions in a message that was not written in the message editor,
+ * as the editor does on submit. Users are only fetched if the message
+ * mentions someone, so scripted posts do not page through the workspace.
+ */
+async fn encode(
+    slack_client: &impl SlackApi,
+    slack_channels: &SlackChannels,
+    message: &str,
+) -> String {
+    let users = if Completer::mentions_users(message) {
+        slack_client.get_users().await.ok()
+    } else {
+        None
+    };
+    Completer::new(
+        &users.unwrap_or_else(|| Vec::new().into()),
+        slack_channels,
+        Vec::new(),
+    )
+    .encode(message)
+}
+
+/*
+ * Parse a channel given on the command line.
+ * Aliases may point to names, IDs or permalinks, so parse again after resolving them.
+ */
+fn channel_ref(config: &Config, input: &str) -> ChannelRef {
+    match ChannelRef::parse(input) {
+        ChannelRef::Name(name) => ChannelRef::parse(&config.resolve_channel(&name)),
+        id => id,
+    }
+}
+
+/*
+ * Post a message or read a channel. `text` starts the editor with a message,
+ * e.g. a rendered template.
+ */
+async fn post_or_read(
+    opts: Opt,
+    config: &Config,
+    slack_client: &impl SlackApi,
+    text: Option<String>,
+) -> Result<()> {
+    let mut message = opts.message.unwrap_or_default();
+    let read_mode = opts.read;
+    let range =
+        timerange::history_range(opts.since.as_deref(), opts.until.as_deref(), opts.inclusive)?;
+    // A time range without an explicit limit reads every message in the range
+    let limit = match (opts.limit, &range.oldest) {
+        (Some(limit), _) => limit,
+        (None, Some(_)) => usize::MAX,
+        (None, None) => DEFAULT_MESSAGE_LIMIT,
+    };
+
+    let inputs = if opts.channel.is_empty() {
+        vec![String::new()]
+    } else {
+        opts.channel
+    };
+    if read_mode && inputs.len() > 1 {
+        anyhow::bail!("Read mode accepts a single channel");
+    }
+    let references: Vec<ChannelRef> = inputs
+        .iter()
+        .map(|input| channel_ref(config, input))
+        .collect();
+
+    let slack_channels = slack_client.get_channels().await?;
+
+    let mut channels: Vec<SlackChannel> = Vec::new();
+    let mut unresolved = Vec::new();
+    for (input, reference) in inputs.iter().zip(&references) {
+        match slack_client
+            .resolve_channel(&slack_channels, reference)
+            .await?
+        {
+            Some(channel) => channels.push(channel),
+            None => unresolved.push(format!("'{}'", input)),
         }
     }
-    selector.draw(&mut stdout, &channel);
+    // Choosing again would drop the channels that were found, so only ask
+    // when none of them were
+    if !channels.is_empty() && !unresolved.is_empty() {
+        anyhow::bail!("Channel not found: {}", unresolved.join(", "));
+    }
+
+    let channel_names = slack_channels.channel_names();
+    let max_col_size = slack_channels.max_channel_size() + 1;
+    let selector = ChannelSelector::new(channel_names.clone(), max_col_size)
+        .with_multi_select(!read_mode)
+        .with_aliases(config.aliases());
 
-    // Get channel ID for API calls that require it
-    let channel_info = channels
-        .find_by_name(&channel)
-        .ok_or_else(|| anyhow!("Channel '{}' not found", channel))?;
+    // The terminal is only taken over when something has to ask the user,
+    // so scripted posts and reads also run without a TTY
+    let mut terminal = Terminal::default();
+
+    if !unresolved.is_empty() {
+        let selected = match selector.run(stdin(), terminal.screen()?)? {
+            SelectionResult::Selected(selected) => vec![selected],
+            SelectionResult::SelectedMany(selected) => selected,
+            SelectionResult::Cancelled => return Ok(()),
+        };
+        channels = selected
+            .iter()
+            .map(|name| {
+                slack_channels
+                    .find_by_name(name)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("Channel '{}' not found", name))
+            })
+            .collect::<Result<_>>()?;
+    }
+    let selected_names: Vec<String> = channels.iter().map(|c| c.name.clone()).collect();
+    if let Some(screen) = terminal.active() {
+        selector.draw_many(screen, &selected_names);
+    }
 
     // Read mode: fetch and display messages
     if read_mode {
-        drop(stdout);
+        drop(terminal);
 
-        let messages = slack_client.get_messages(&channel_info.id, limit).await?;
+        let channel = &channels[0];
+        let messages = slack_client
+            .get_messages(&channel.id, limit, &range)
+            .await?;
 
-        print_messages(&channel, &messages.messages);
+        print_messages(&channel.name, &messages.messages);
         return Ok(());
     }
 
-    // Write mode: compose and post message
-    if Editor::needs_input(&message) {
-        let mut editor = Editor::new();
-        match editor.run(stdin(), &mut stdout, &channel)? {
-            EditorResult::Submitted(msg) => message = msg,
-            EditorResult::Cancelled => return Ok(()),
+    // Write mode: compose and post message.
+    // A message composed in the editor is kept as a draft until it is posted.
+    let mut draft = None;
+    let ids: Vec<&str> = channels.iter().map(|c| c.id.as_str()).collect();
+    // The history only helps composing, so one that cannot be read is left out
+    let mut sent = SentHistory::open_default(config.history_size()).ok();
+    // The history keeps the message as typed; mentions are encoded again when it is reused
+    let typed;
+    if opts.editor {
+        typed = external_editor::edit_in_terminal(terminal.screen()?, &message)?;
+        message = encode(slack_client, &slack_channels, &typed).await;
+        if Editor::needs_input(&message)
+            || !confirm_post(
+                opts.yes || opts.dry_run,
+                config,
+                &channels,
+                &message,
+                &mut terminal,
+            )?
+        {
+            return Ok(());
+        }
+    } else if Editor::needs_input(&message) {
+        let mut editor = Editor::new()
+            .with_vi_mode(config.vi_mode())
+            .with_completer(completer(slack_client, &slack_channels).await)
+            .with_history(sent.as_ref().map(|s| s.recall(&ids)).unwrap_or_default());
+        if let Ok(store) = DraftStore::open_default() {
+            let key = DraftStore::key(&ids);
+            editor = editor.with_draft(store.clone(), &key);
+            draft = Some((store, key));
+        }
+        if let Some(text) = &text {
+            editor.set_message(text);
+        }
+        loop {
+            match editor.run(stdin(), terminal.screen()?, &selected_names.join(", #"))? {
+                // Declining the confirmation goes back to the editor
+                EditorResult::Submitted(msg) => {
+                    if confirm_post(
+                        opts.yes || opts.dry_run,
+                        config,
+                        &channels,
+                        &msg,
+                        &mut terminal,
+                    )? {
+                        message = msg;
+                        break;
+                    }
+                }
+                EditorResult::Cancelled => return Ok(()),
+                EditorResult::External(text) => {
+                    let edited = external_editor::edit_in_terminal(terminal.screen()?, &text)?;
+                    editor.set_message(&edited);
+                }
+            }
+        }
+        typed = editor.message();
+    } else {
+        typed = message.clone();
+        message = encode(slack_client, &slack_channels, &typed).await;
+        if !confirm_post(
+            opts.yes || opts.dry_run,
+            config,
+            &channels,
+            &message,
+            &mut terminal,
+        )? {
+            return Ok(());
         }
     }
 
-    drop(stdout);
+    drop(terminal);
+
+    let thread_ts = opts.thread.as_deref();
+    if opts.dry_run {
+        for part in split_message(&message, MAX_MESSAGE_LENGTH) {
+            for channel in &channels {
+                println!("[Dry run] #{} ({})", channel.name, channel.id);
+                println!(
+                    "{}",
+                    slack_client.describe_post_message(&channel.id, &part, thread_ts)?
+                );
+            }
+        }
+        return Ok(());
+    }
 
-    slack_client.post_message(&channel, &message).await?;
-    println!("[Success] #{}\n {}", channel, message);
+    // Earlier posts go first so that messages arrive in order
+    flush_outbox(slack_client).await;
+
+    // Post overlong messages in parts. A channel gets the next part only if
+    // the previous one was posted.
+    let parts = split_message(&message, MAX_MESSAGE_LENGTH);
+    let total = channels.len();
+    let mut posted = channels;
+    let mut failed = Vec::new();
+    let mut offline = Vec::new();
+    let mut queued = Vec::new();
+    for (index, part) in parts.iter().enumerate() {
+        let results = slack_client
+            .post_message_to_channels(&posted, part, thread_ts, MAX_CONCURRENT_POSTS)
+            .await;
+        posted = Vec::new();
+        for (channel, result) in results {
+            match result {
+                Ok(_) => posted.push(channel),
+                // Queue this part and the rest when Slack could not be reached
+                Err(err) if is_transient(&err) => {
+                    queued.extend(parts[index..].iter().map(|part| {
+                        QueuedMessage::new(&channel.id, &channel.name, part, thread_ts)
+                    }));
+                    offline.push((channel, err));
+                }
+                Err(err) => failed.push((channel, err)),
+            }
+        }
+    }
+
+    if !queued.is_empty() {
+        match Outbox::open_default().and_then(|mut outbox| outbox.push(queued)) {
+            Ok(()) => {
+                for (channel, err) in &offline {
+                    eprintln!("[Queued] #{}: {}", channel.name, err);
+                }
+            }
+            Err(err) => {
+                eprintln!("[Warning] Could not queue the message: {}", err);
+                failed.append(&mut offline);
+            }
+        }
+    }
+
+    for channel in &posted {
+        println!("[Success] #{}", channel.name);
+        if let Some(sent) = sent.as_mut() {
+            if let Err(err) = sent.record(&channel.id, &typed.replace("\r\n", "\n")) {
+                eprintln!("[Warning] Could not save the sent message: {}", err);
+            }
+        }
+    }
+    for (channel, err) in &failed {
+        eprintln!("[Failure] #{}: {}", channel.name, err);
+    }
+    if !posted.is_empty() {
+        println!(" {}", message);
+    }
+
+    if !failed.is_empty() {
+        anyhow::bail!("Failed to post to {} of {} channels", failed.len(), total);
+    }
+    // Queued messages are kept in the outbox, so the draft is no longer needed
+    if let Some((store, key)) = draft {
+        store.delete(&key).ok();
+    }
+    if !offline.is_empty() {
+        anyhow::bail!(
+            "Slack could not be reached; queued the message for {} of {} channels. \
+             It is posted on the next run or with `rslack outbox flush`",
+            offline.len(),
+            total
+        );
+    }
 
     Ok(())
 }
+
+/*
+ * Ask before posting to protected channels, notifying whole channels
+ * or splitting an overlong message, unless --yes was given.
+ * Returns true if the message should be posted.
+ */
+fn confirm_post(
+    yes: bool,
+    config: &Config,
+    channels: &[SlackChannel],
+    message: &str,
+    terminal: &mut Terminal,
+) -> Result<bool> {
+    if yes {
+        return Ok(true);
+    }
+
+    let reasons = prompt::post_warnings(config, channels, message);
+    if reasons.is_empty() {
+        return Ok(true);
+    }
+    prompt::confirm(stdin(), terminal.screen()?, "Post the message?", &reasons)
+}
+
+/*
+ * The terminal in raw mode on the alternate screen, entered on first use
+ * and restored when dropped.
+ */
+#[derive(Default)]
+struct Terminal {
+    screen: Option<external_editor::Screen>,
+}
+
+impl Terminal {
+    /*
+     * Screen to draw on, entering raw mode and the alternate screen if needed.
+     */
+    fn screen(&mut self) -> Result<&mut external_editor::Screen> {
+        if self.screen.is_none() {
+            self.screen = Some(stdout().into_raw_mode()?.into_alternate_screen()?);
+        }
+        Ok(self.screen.as_mut().unwrap())
+    }
+
+    /*
+     * Screen to draw on, if the terminal was already entered.
+     */
+    fn active(&mut self) -> Option<&mut external_editor::Screen> {
+        self.screen.as_mut()
+    }
+}
@@ -1,26 +1,66 @@
 use anyhow::{anyhow, Result};
 use dirs;
+use std::collections::BTreeMap;
 use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 const RSLACK_TOKEN: &str = "RSLACK_TOKEN";
+const RSLACK_DEFAULT_CHANNEL: &str = "RSLACK_DEFAULT_CHANNEL";
+const RSLACK_HISTORY_SIZE: &str = "RSLACK_HISTORY_SIZE";
+const RSLACK_KEYBINDINGS: &str = "RSLACK_KEYBINDINGS";
+const RSLACK_PROTECTED_CHANNELS: &str = "RSLACK_PROTECTED_CHANNELS";
+const RSLACK_CONFIRM_BROADCASTS: &str = "RSLACK_CONFIRM_BROADCASTS";
+const RSLACK_BASE_URL: &str = "RSLACK_BASE_URL";
+const RSLACK_TIMEOUT: &str = "RSLACK_TIMEOUT";
+const RSLACK_CONNECT_TIMEOUT: &str = "RSLACK_CONNECT_TIMEOUT";
+const RSLACK_PROXY: &str = "RSLACK_PROXY";
+const RSLACK_CA_BUNDLE: &str = "RSLACK_CA_BUNDLE";
+const RSLACK_USER_AGENT: &str = "RSLACK_USER_AGENT";
 const CONFIG_FILE: &str = ".rslack";
 
+/// Config file section holding `alias = channel` entries
+const SECTION_ALIASES: &str = "aliases";
+
+/// Config file section holding `name = template` entries
+const SECTION_TEMPLATES: &str = "templates";
+
+/// Number of sent messages remembered unless configured otherwise
+pub const DEFAULT_HISTORY_SIZE: usize = 500;
+
 /// Default Slack API base URL
 pub const SLACK_URL: &str = "https://slack.com";
 
+/// Time allowed for a whole request unless configured otherwise
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time allowed for connecting to Slack unless configured otherwise
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Default)]
 #[cfg_attr(test, derive(Debug, PartialEq))]
 pub struct Config {
     token: String,
+    default_channel: Option<String>,
+    aliases: BTreeMap<String, String>,
+    templates: BTreeMap<String, String>,
+    history_size: Option<usize>,
+    vi_mode: bool,
+    protected_channels: Vec<String>,
+    confirm_broadcasts: bool,
+    base_url: Option<String>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    proxy: Option<String>,
+    ca_bundle: Option<PathBuf>,
+    user_agent: Option<String>,
 }
 
 impl Config {
     pub fn new(config_path: Option<PathBuf>) -> Result<Self> {
-        let mut config = Self {
-            token: String::new(),
-        };
+        let mut config = Self::default();
 
         config.read_from_env()?;
 
@@ -43,10 +83,51 @@ impl Config {
             Ok(token) => self.token = token,
             Err(_) => {}
         }
+        match env::var(RSLACK_DEFAULT_CHANNEL) {
+            Ok(channel) if !channel.trim().is_empty() => {
+                self.default_channel = Some(channel.trim().to_string())
+            }
+            _ => {}
+        }
+        if let Ok(size) = env::var(RSLACK_HISTORY_SIZE) {
+            self.history_size = parse_history_size(&size);
+        }
+        if let Ok(keybindings) = env::var(RSLACK_KEYBINDINGS) {
+            self.vi_mode = is_vi(&keybindings);
+        }
+        if let Ok(channels) = env::var(RSLACK_PROTECTED_CHANNELS) {
+            self.protected_channels = parse_channel_list(&channels);
+        }
+        if let Ok(confirm) = env::var(RSLACK_CONFIRM_BROADCASTS) {
+            self.confirm_broadcasts = is_true(&confirm);
+        }
+        if let Ok(url) = env::var(RSLACK_BASE_URL) {
+            self.base_url = non_empty(&url);
+        }
+        if let Ok(seconds) = env::var(RSLACK_TIMEOUT) {
+            self.timeout = parse_seconds(&seconds);
+        }
+        if let Ok(seconds) = env::var(RSLACK_CONNECT_TIMEOUT) {
+            self.connect_timeout = parse_seconds(&seconds);
+        }
+        if let Ok(proxy) = env::var(RSLACK_PROXY) {
+            self.proxy = non_empty(&proxy);
+        }
+        if let Ok(path) = env::var(RSLACK_CA_BUNDLE) {
+            self.ca_bundle = non_empty(&path).map(PathBuf::from);
+        }
+        if let Ok(user_agent) = env::var(RSLACK_USER_AGENT) {
+            self.user_agent = non_empty(&user_agent);
+        }
 
         Ok(self)
     }
 
+    /*
+     * Read `KEY = value` lines. `[section]` headers switch to a named table,
+     * e.g. `[aliases]` followed by `alerts = "team-platform-alerts-prod"`
+     * or `[templates]` followed by `deploy = "Deploying {{service}}"`.
+     */
     #[allow(clippy::single_match)]
     fn read_from_file(&mut self, path: &Path) -> Result<&Self> {
         let file = match File::open(path) {
@@ -54,17 +135,43 @@ impl Config {
             Err(err) => anyhow::bail!("{}: {}", path.display(), err),
         };
 
-        for line in BufReader::new(file).lines() {
-            if let Ok(line) = line {
-                let entries: Vec<_> = line.split('=').map(str::trim).collect();
+        let mut section = String::new();
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
 
-                if entries.len() == 2 {
-                    let (key, val) = (entries[0].trim(), entries[1].trim().to_string());
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
 
-                    match key {
-                        RSLACK_TOKEN => self.token = val,
-                        _ => {}
+            if let Some((key, val)) = line.split_once('=') {
+                let (key, val) = (key.trim(), unquote(val.trim()).to_string());
+
+                match (section.as_str(), key) {
+                    ("", RSLACK_TOKEN) => self.token = val,
+                    ("", RSLACK_DEFAULT_CHANNEL) => self.default_channel = Some(val),
+                    ("", RSLACK_HISTORY_SIZE) => self.history_size = parse_history_size(&val),
+                    ("", RSLACK_KEYBINDINGS) => self.vi_mode = is_vi(&val),
+                    ("", RSLACK_PROTECTED_CHANNELS) => {
+                        self.protected_channels = parse_channel_list(&val)
+                    }
+                    ("", RSLACK_CONFIRM_BROADCASTS) => self.confirm_broadcasts = is_true(&val),
+                    ("", RSLACK_BASE_URL) => self.base_url = non_empty(&val),
+                    ("", RSLACK_TIMEOUT) => self.timeout = parse_seconds(&val),
+                    ("", RSLACK_CONNECT_TIMEOUT) => self.connect_timeout = parse_seconds(&val),
+                    ("", RSLACK_PROXY) => self.proxy = non_empty(&val),
+                    ("", RSLACK_CA_BUNDLE) => self.ca_bundle = non_empty(&val).map(PathBuf::from),
+                    ("", RSLACK_USER_AGENT) => self.user_agent = non_empty(&val),
+                    (SECTION_ALIASES, alias) => {
+                        self.aliases.insert(alias.to_string(), val);
                     }
+                    (SECTION_TEMPLATES, name) => {
+                        self.templates.insert(name.to_string(), val);
+                    }
+                    _ => {}
                 }
             }
         }
@@ -76,6 +183,91 @@ impl Config {
         &self.token
     }
 
+    pub fn default_channel(&self) -> Option<&str> {
+        self.default_channel.as_deref()
+    }
+
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.aliases
+    }
+
+    /// Message template of the given name (`[templates]` section)
+    pub fn template(&self, name: &str) -> Option<&str> {
+        self.templates.get(name).map(String::as_str)
+    }
+
+    /// Number of sent messages to remember; 0 disables the history
+    pub fn history_size(&self) -> usize {
+        self.history_size.unwrap_or(DEFAULT_HISTORY_SIZE)
+    }
+
+    /// Use vi keybindings in the message editor (`RSLACK_KEYBINDINGS = vi`)
+    pub fn vi_mode(&self) -> bool {
+        self.vi_mode
+    }
+
+    /// Check if posting to a channel needs confirmation (`RSLACK_PROTECTED_CHANNELS`)
+    pub fn is_protected(&self, channel_name: &str) -> bool {
+        self.protected_channels
+            .iter()
+            .any(|protected| protected == channel_name)
+    }
+
+    /// Check if messages mentioning @channel, @here or @everyone need confirmation
+    pub fn confirm_broadcasts(&self) -> bool {
+        self.confirm_broadcasts
+    }
+
+    /// Slack API base URL (`RSLACK_BASE_URL`), e.g. for GovSlack or a mock server
+    pub fn base_url(&self) -> &str {
+        self.base_url.as_deref().unwrap_or(SLACK_URL)
+    }
+
+    /// Time allowed for a whole request (`RSLACK_TIMEOUT`, in seconds)
+    pub fn timeout(&self) -> Duration {
+        self.timeout.unwrap_or(DEFAULT_TIMEOUT)
+    }
+
+    /// Time allowed for connecting (`RSLACK_CONNECT_TIMEOUT`, in seconds)
+    pub fn connect_timeout(&self) -> Duration {
+        self.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT)
+    }
+
+    /// Proxy for all requests (`RSLACK_PROXY`).
+    /// Without one, `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` are used.
+    pub fn proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    /// PEM file with certificates to trust in addition to the built-in ones (`RSLACK_CA_BUNDLE`)
+    pub fn ca_bundle(&self) -> Option<&Path> {
+        self.ca_bundle.as_deref()
+    }
+
+    /// User-Agent header sent with every request (`RSLACK_USER_AGENT`)
+    pub fn user_agent(&self) -> String {
+        self.user_agent
+            .clone()
+            .unwrap_or_else(|| format!("rslack/{}", env!("CARGO_PKG_VERSION")))
+    }
+
+    /// Resolve a channel given on the command line.
+    /// An empty channel falls back to the default channel, and aliases are
+    /// replaced with the channel they point to.
+    pub fn resolve_channel(&self, channel: &str) -> String {
+        let channel = channel.trim();
+        let channel = if channel.is_empty() {
+            self.default_channel().unwrap_or_default()
+        } else {
+            channel
+        };
+
+        match self.aliases.get(channel) {
+            Some(target) => target.clone(),
+            None => channel.to_string(),
+        }
+    }
+
     fn validate(&mut self) -> Result<()> {
         if self.token.is_empty() {
             anyhow::bail!("{} not found.", RSLACK_TOKEN);
@@ -85,13 +277,95 @@ impl Config {
     }
 }
 
+/// Directory for local state such as drafts, e.g. `~/.local/state/rslack`.
+/// Platforms without a state directory use the local data directory.
+pub fn state_dir() -> Result<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join("rslack"))
+        .ok_or(anyhow!("State directory not found."))
+}
+
+/*
+ * History size from the environment or the config file.
+ * Values that are not a number fall back to the default size.
+ */
+fn parse_history_size(val: &str) -> Option<usize> {
+    val.trim().parse().ok()
+}
+
+/*
+ * Keybindings are `vi` or anything else for the default ones.
+ */
+fn is_vi(val: &str) -> bool {
+    val.trim().eq_ignore_ascii_case("vi")
+}
+
+/*
+ * Comma separated channel names, with or without `#`.
+ */
+fn parse_channel_list(val: &str) -> Vec<String> {
+    val.split(',')
+        .map(|name| name.trim().trim_start_matches('#').to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/*
+ * Timeouts in seconds, e.g. `30` or `2.5`.
+ * Values that are not a positive number fall back to the default timeout.
+ */
+fn parse_seconds(val: &str) -> Option<Duration> {
+    val.trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+fn non_empty(val: &str) -> Option<String> {
+    Some(val.trim().to_string()).filter(|val| !val.is_empty())
+}
+
+fn is_true(val: &str) -> bool {
+    matches!(val.trim().to_lowercase().as_str(), "true" | "yes" | "1")
+}
+
+/*
+ * Strip a single pair of surrounding quotes.
+ */
+fn unquote(val: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|q| val.strip_prefix(*q).and_then(|v| v.strip_suffix(*q)))
+        .unwrap_or(val)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serial_test::serial;
 
+    /* Every variable Config::new reads, so none leaks from one test into the next */
+    const RSLACK_KEYS: [&str; 12] = [
+        RSLACK_TOKEN,
+        RSLACK_DEFAULT_CHANNEL,
+        RSLACK_HISTORY_SIZE,
+        RSLACK_KEYBINDINGS,
+        RSLACK_PROTECTED_CHANNELS,
+        RSLACK_CONFIRM_BROADCASTS,
+        RSLACK_BASE_URL,
+        RSLACK_TIMEOUT,
+        RSLACK_CONNECT_TIMEOUT,
+        RSLACK_PROXY,
+        RSLACK_CA_BUNDLE,
+        RSLACK_USER_AGENT,
+    ];
+
     fn setup() {
-        env::remove_var(RSLACK_TOKEN);
+        for key in RSLACK_KEYS {
+            env::remove_var(key);
+        }
     }
 
     #[test]
@@ -103,6 +377,7 @@ mod tests {
         let actual = Config::new(Some(config_path)).unwrap();
         let expected = Config {
             token: String::from("token-from-file-123"),
+            ..Default::default()
         };
         assert_eq!(actual, expected);
     }
@@ -127,6 +402,7 @@ mod tests {
         let actual = Config::new(Some(config_path)).unwrap();
         let expected = Config {
             token: String::from("token-from-env-123"),
+            ..Default::default()
         };
         assert_eq!(actual, expected);
     }
@@ -150,6 +426,7 @@ mod tests {
 
         let expected = Config {
             token: String::from("token-from-file-123"),
+            ..Default::default()
         };
         let config_path = PathBuf::from("tests/fixtures/config/config.test.valid");
         let actual = Config::new(Some(config_path)).unwrap();
@@ -160,6 +437,7 @@ mod tests {
     fn valid_token() {
         let mut config = Config {
             token: String::from("token"),
+            ..Default::default()
         };
         let actual = config.validate();
         assert!(actual.is_ok())
@@ -169,8 +447,163 @@ mod tests {
     fn invalid_token() {
         let mut config = Config {
             token: String::new(),
+            ..Default::default()
         };
         let actual = config.validate();
         assert!(actual.is_err())
     }
+
+    #[test]
+    #[serial]
+    fn initialize_with_default_channel_and_aliases() {
+        setup();
+
+        let config_path = PathBuf::from("tests/fixtures/config/config.test.aliases");
+        let actual = Config::new(Some(config_path)).unwrap();
+        assert_eq!(actual.default_channel(), Some("general"));
+        assert_eq!(
+            actual.aliases().get("alerts").map(String::as_str),
+            Some("team-platform-alerts-prod")
+        );
+        assert_eq!(
+            actual.aliases().get("rand").map(String::as_str),
+            Some("random")
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn initialize_with_templates() {
+        setup();
+
+        let config_path = PathBuf::from("tests/fixtures/config/config.test.templates");
+        let actual = Config::new(Some(config_path)).unwrap();
+        assert_eq!(
+            actual.template("deploy"),
+            Some("Deploying {{service}} {{version}} to {{env}}")
+        );
+        assert_eq!(
+            actual.template("incident"),
+            Some(":rotating_light: {{summary}}\\nOwner: {{owner}}")
+        );
+        assert_eq!(actual.template("deploys"), None);
+        assert_eq!(
+            actual.aliases().get("deploys").map(String::as_str),
+            Some("team-deploys")
+        );
+    }
+
+    #[test]
+    fn resolve_channel_with_default_and_aliases() {
+        let config = Config {
+            token: String::from("token"),
+            default_channel: Some(String::from("general")),
+            aliases: BTreeMap::from([(
+                String::from("alerts"),
+                String::from("team-platform-alerts-prod"),
+            )]),
+            ..Default::default()
+        };
+        assert_eq!(config.resolve_channel(""), "general");
+        assert_eq!(
+            config.resolve_channel("alerts"),
+            "team-platform-alerts-prod"
+        );
+        assert_eq!(config.resolve_channel("random"), "random");
+    }
+
+    #[test]
+    fn resolve_channel_without_default() {
+        let config = Config::default();
+        assert_eq!(config.resolve_channel("  "), "");
+    }
+
+    #[test]
+    #[serial]
+    fn history_size_from_env_and_file() {
+        setup();
+
+        let config_path = PathBuf::from("tests/fixtures/config/config.test.valid");
+        let actual = Config::new(Some(config_path)).unwrap();
+        assert_eq!(actual.history_size(), DEFAULT_HISTORY_SIZE);
+
+        env::set_var(RSLACK_HISTORY_SIZE, "0");
+        let config_path = PathBuf::from("tests/fixtures/config/config.test.valid");
+        let actual = Config::new(Some(config_path)).unwrap();
+        assert_eq!(actual.history_size(), 0);
+
+        let config_path = PathBuf::from("tests/fixtures/config/config.test.options");
+        let actual = Config::new(Some(config_path)).unwrap();
+        assert_eq!(actual.history_size(), 100);
+    }
+
+    #[test]
+    #[serial]
+    fn vi_mode_from_env_and_file() {
+        setup();
+
+        let config_path = PathBuf::from("tests/fixtures/config/config.test.valid");
+        assert!(!Config::new(Some(config_path)).unwrap().vi_mode());
+
+        env::set_var(RSLACK_KEYBINDINGS, "VI");
+        let config_path = PathBuf::from("tests/fixtures/config/config.test.valid");
+        assert!(Config::new(Some(config_path)).unwrap().vi_mode());
+
+        let config_path = PathBuf::from("tests/fixtures/config/config.test.options");
+        assert!(!Config::new(Some(config_path)).unwrap().vi_mode());
+    }
+
+    #[test]
+    #[serial]
+    fn confirmations_from_file() {
+        setup();
+
+        let config_path = PathBuf::from("tests/fixtures/config/config.test.valid");
+        let actual = Config::new(Some(config_path)).unwrap();
+        assert!(!actual.is_protected("general"));
+        assert!(!actual.confirm_broadcasts());
+
+        let config_path = PathBuf::from("tests/fixtures/config/config.test.options");
+        let actual = Config::new(Some(config_path)).unwrap();
+        assert!(actual.is_protected("general"));
+        assert!(actual.is_protected("announcements"));
+        assert!(!actual.is_protected("random"));
+        assert!(actual.confirm_broadcasts());
+    }
+
+    #[test]
+    #[serial]
+    fn http_settings_from_env_and_file() {
+        setup();
+
+        let config_path = PathBuf::from("tests/fixtures/config/config.test.valid");
+        let actual = Config::new(Some(config_path)).unwrap();
+        assert_eq!(actual.base_url(), SLACK_URL);
+        assert_eq!(actual.timeout(), DEFAULT_TIMEOUT);
+        assert_eq!(actual.connect_timeout(), DEFAULT_CONNECT_TIMEOUT);
+        assert_eq!(actual.proxy(), None);
+        assert_eq!(actual.ca_bundle(), None);
+        assert!(actual.user_agent().starts_with("rslack/"));
+
+        let config_path = PathBuf::from("tests/fixtures/config/config.test.http");
+        let actual = Config::new(Some(config_path)).unwrap();
+        assert_eq!(actual.base_url(), "https://slack-gov.com");
+        assert_eq!(actual.timeout(), Duration::from_secs(60));
+        assert_eq!(actual.connect_timeout(), Duration::from_millis(2500));
+        assert_eq!(actual.proxy(), Some("http://proxy.example.com:8080"));
+        assert_eq!(
+            actual.ca_bundle(),
+            Some(Path::new("tests/fixtures/tls/ca.pem"))
+        );
+        assert_eq!(actual.user_agent(), "rslack-corp/1.0");
+
+        let config_path = PathBuf::from("tests/fixtures/config/config.test.valid");
+        env::set_var(RSLACK_BASE_URL, "http://127.0.0.1:8787");
+        env::set_var(RSLACK_TIMEOUT, "not a number");
+        let actual = Config::new(Some(config_path)).unwrap();
+        env::remove_var(RSLACK_BASE_URL);
+        env::remove_var(RSLACK_TIMEOUT);
+        assert_eq!(actual.base_url(), "http://127.0.0.1:8787");
+        assert_eq!(actual.timeout(), DEFAULT_TIMEOUT);
+    }
 }
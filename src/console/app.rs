@@ -0,0 +1,1023 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::thread;
+
+use anyhow::Result;
+use termion::event::Key;
+use termion::{clear, color, cursor, style};
+use tokio::sync::mpsc;
+use unicode_width::UnicodeWidthChar;
+
+use super::input::{inputs, Input, DISABLE_BRACKETED_PASTE, ENABLE_BRACKETED_PASTE};
+use super::prompt::post_warnings;
+use super::{Completer, Editor, EditorResult, MessageViewer};
+use crate::config::Config;
+use crate::draft::DraftStore;
+use crate::outbox::{Outbox, QueuedMessage};
+use crate::slack::{
+    is_transient, split_message, HistoryRange, SlackApi, SlackChannel, SlackMessage,
+    MAX_MESSAGE_LENGTH,
+};
+
+const USAGE_APP: &str = "(focus: tab / post: ctrl-p / exit: ctrl-c)";
+/// Rows used by the composer, including its border
+const COMPOSER_HEIGHT: u16 = 6;
+/// Number of messages loaded into the history pane
+const HISTORY_LIMIT: usize = 50;
+
+/// Pane that receives key input
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Focus {
+    Channels,
+    History,
+    Composer,
+}
+
+impl Focus {
+    fn next(self) -> Self {
+        match self {
+            Focus::Channels => Focus::History,
+            Focus::History => Focus::Composer,
+            Focus::Composer => Focus::Channels,
+        }
+    }
+
+    fn prev(self) -> Self {
+        match self {
+            Focus::Channels => Focus::Composer,
+            Focus::History => Focus::Channels,
+            Focus::Composer => Focus::History,
+        }
+    }
+}
+
+/// History of a channel as shown in the history pane
+pub enum History {
+    Loading,
+    Loaded(Vec<SlackMessage>),
+    Failed(String),
+}
+
+/// Work the event loop has to do in response to a key
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    /// Fetch the history of the channel with this ID
+    LoadHistory(String),
+    /// Post the text to the channel with this ID, split into parts if it is too long
+    Post(String, String),
+    Quit,
+}
+
+/// Events handled by the event loop
+enum Event {
+    Key(Key),
+    Paste(String),
+    History(String, Result<Vec<SlackMessage>>),
+    /// Channel ID and the result; a failure comes with the number of parts posted before it
+    Posted(String, Result<(), (usize, anyhow::Error)>),
+}
+
+/// Full-screen interface with a channel sidebar, a history pane and a composer
+pub struct App {
+    channels: Vec<SlackChannel>,
+    selected: usize,
+    focus: Focus,
+    histories: HashMap<String, History>,
+    scroll: usize,
+    editor: Editor,
+    status: String,
+    drafts: Option<DraftStore>,
+    /// Protected channels and broadcast settings checked before posting
+    config: Option<Config>,
+    /// Message whose warnings were shown; posting it again goes ahead
+    confirming: Option<String>,
+    /// Posts that have not finished, by channel ID: the text as typed and as posted
+    posting: HashMap<String, (String, String)>,
+    outbox: Option<Outbox>,
+}
+
+impl App {
+    pub fn new(channels: Vec<SlackChannel>) -> Self {
+        Self {
+            channels,
+            selected: 0,
+            focus: Focus::Channels,
+            histories: HashMap::new(),
+            scroll: 0,
+            editor: Editor::new(),
+            status: String::new(),
+            drafts: None,
+            config: None,
+            confirming: None,
+            posting: HashMap::new(),
+            outbox: None,
+        }
+    }
+
+    /// Complete @mentions, #channels and :emoji: in the composer
+    pub fn with_completer(mut self, completer: Completer) -> Self {
+        self.editor = Editor::new().with_completer(completer);
+        self
+    }
+
+    /// Keep unsent composer text per channel and restore it when composing again
+    pub fn with_drafts(mut self, drafts: DraftStore) -> Self {
+        self.drafts = Some(drafts);
+        self
+    }
+
+    /// Queue posts that fail because Slack cannot be reached
+    pub fn with_outbox(mut self, outbox: Outbox) -> Self {
+        self.outbox = Some(outbox);
+        self
+    }
+
+    /// Ask for a second Ctrl-P before posting to protected channels,
+    /// broadcasting with @channel or @here, or posting text over Slack's limit
+    pub fn with_post_checks(mut self, config: &Config) -> Self {
+        self.config = Some(config.clone());
+        self
+    }
+
+    pub fn focus(&self) -> Focus {
+        self.focus
+    }
+
+    pub fn selected_channel(&self) -> Option<&SlackChannel> {
+        self.channels.get(self.selected)
+    }
+
+    /// Select the channel with the given name, if it exists
+    pub fn select(&mut self, name: &str) {
+        if let Some(index) = self.channels.iter().position(|c| c.name == name) {
+            self.selected = index;
+        }
+    }
+
+    /// Show a message in the composer's status line
+    pub fn set_status(&mut self, status: &str) {
+        self.status = status.to_string();
+    }
+
+    /// Request the history of the selected channel unless it is already known
+    pub fn load_selected(&mut self) -> Option<Action> {
+        let id = self.selected_channel()?.id.clone();
+        if self.histories.contains_key(&id) {
+            return None;
+        }
+
+        self.histories.insert(id.clone(), History::Loading);
+        Some(Action::LoadHistory(id))
+    }
+
+    pub fn set_history(&mut self, channel_id: &str, history: Result<Vec<SlackMessage>>) {
+        let history = match history {
+            Ok(messages) => History::Loaded(messages),
+            Err(err) => History::Failed(err.to_string()),
+        };
+        self.histories.insert(channel_id.to_string(), history);
+    }
+
+    /// Apply a key to the focused pane
+    pub fn handle_key(&mut self, key: Key) -> Option<Action> {
+        match key {
+            Key::Ctrl('c') => return Some(Action::Quit),
+            // Tab completes in the composer and switches panes otherwise
+            Key::Char('\t') if self.focus == Focus::Composer && self.editor.complete() => {
+                self.save_draft();
+                return None;
+            }
+            Key::Char('\t') => {
+                self.focus = self.focus.next();
+                return None;
+            }
+            Key::BackTab => {
+                self.focus = self.focus.prev();
+                return None;
+            }
+            _ => {}
+        }
+
+        match self.focus {
+            Focus::Channels => self.handle_channels_key(key),
+            Focus::History => {
+                self.handle_history_key(key);
+                None
+            }
+            Focus::Composer => self.handle_composer_key(key),
+        }
+    }
+
+    fn handle_channels_key(&mut self, key: Key) -> Option<Action> {
+        match key {
+            Key::Char('q') => return Some(Action::Quit),
+            Key::Up | Key::Char('k') if self.selected > 0 => self.selected -= 1,
+            Key::Down | Key::Char('j') if self.selected + 1 < self.channels.len() => {
+                self.selected += 1
+            }
+            Key::Char('r') => {
+                let id = self.selected_channel()?.id.clone();
+                self.histories.remove(&id);
+            }
+            Key::Char('\n') => {
+                self.focus = Focus::Composer;
+                self.restore_draft();
+                return None;
+            }
+            _ => return None,
+        }
+
+        self.scroll = 0;
+        self.load_selected()
+    }
+
+    fn handle_history_key(&mut self, key: Key) {
+        match key {
+            Key::Up | Key::Char('k') => self.scroll += 1,
+            Key::Down | Key::Char('j') => self.scroll = self.scroll.saturating_sub(1),
+            Key::PageUp => self.scroll += 10,
+            Key::PageDown => self.scroll = self.scroll.saturating_sub(10),
+            Key::Char('G') | Key::End => self.scroll = 0,
+            _ => {}
+        }
+    }
+
+    fn handle_composer_key(&mut self, key: Key) -> Option<Action> {
+        if key == Key::Esc {
+            self.focus = Focus::Channels;
+            return None;
+        }
+
+        let Some(result) = self.editor.handle_key(key, &mut io::sink()) else {
+            self.save_draft();
+            return None;
+        };
+        match result {
+            EditorResult::Submitted(text) => {
+                let channel = self.selected_channel()?.clone();
+                if self.posting.contains_key(&channel.id) {
+                    self.status = format!("Still posting to #{}...", channel.name);
+                    return None;
+                }
+                let warnings = match &self.config {
+                    Some(config) => post_warnings(config, std::slice::from_ref(&channel), &text),
+                    None => Vec::new(),
+                };
+                if !warnings.is_empty() && self.confirming.as_ref() != Some(&text) {
+                    self.status = format!("{} Press ctrl-p again to post.", warnings.join(" "));
+                    self.confirming = Some(text);
+                    return None;
+                }
+
+                let id = channel.id;
+                self.confirming = None;
+                // The text is kept here instead of in the draft until the post is done,
+                // so it is not restored and posted twice meanwhile
+                let typed = self.editor.lines().join("\n");
+                self.editor.clear(&mut io::sink());
+                self.save_draft();
+                self.posting.insert(id.clone(), (typed, text.clone()));
+                self.status = "Posting...".to_string();
+                Some(Action::Post(id, text))
+            }
+            EditorResult::Cancelled => Some(Action::Quit),
+            EditorResult::External(_) => {
+                self.status = "$EDITOR is not available in this mode".to_string();
+                None
+            }
+        }
+    }
+
+    /// Insert pasted text into the composer. Pastes into other panes are ignored
+    /// so pasted text never acts as key commands.
+    pub fn handle_paste(&mut self, text: &str) {
+        if self.focus == Focus::Composer {
+            self.editor.paste(text);
+            self.save_draft();
+        }
+    }
+
+    /*
+     * Keep a message that could not be posted. Parts left when Slack could not
+     * be reached go to the outbox; otherwise the text goes back to the composer,
+     * as typed if nothing was posted yet.
+     */
+    fn post_failed(&mut self, id: &str, posted: usize, err: anyhow::Error) {
+        let Some((typed, text)) = self.posting.remove(id) else {
+            return;
+        };
+        let name = self
+            .channels
+            .iter()
+            .find(|channel| channel.id == id)
+            .map_or(id, |channel| channel.name.as_str())
+            .to_string();
+        let unsent = split_message(&text, MAX_MESSAGE_LENGTH).split_off(posted);
+
+        if is_transient(&err) {
+            if let Some(outbox) = self.outbox.as_mut() {
+                let queued = unsent
+                    .iter()
+                    .map(|part| QueuedMessage::new(id, &name, part, None))
+                    .collect();
+                if outbox.push(queued).is_ok() {
+                    self.status = format!(
+                        "[Queued] #{}: {}. It is posted with `rslack outbox flush`",
+                        name, err
+                    );
+                    return;
+                }
+            }
+        }
+
+        let restored = if posted == 0 {
+            typed
+        } else {
+            unsent.join("\n")
+        };
+        let place = self.restore_unsent(id, &restored);
+        self.status = format!(
+            "[Failure] #{}: {}. The message is back in {}",
+            name, err, place
+        );
+    }
+
+    /*
+     * Put text in front of the composer if its channel is selected, or of the
+     * channel's draft otherwise. Returns where the text went.
+     */
+    fn restore_unsent(&mut self, id: &str, text: &str) -> &'static str {
+        let join = |newer: String| {
+            if newer.is_empty() {
+                text.to_string()
+            } else {
+                format!("{}\n{}", text, newer)
+            }
+        };
+
+        if self
+            .selected_channel()
+            .is_some_and(|channel| channel.id != id)
+        {
+            if let Some(drafts) = &self.drafts {
+                let draft = join(drafts.load(id).unwrap_or_default());
+                if drafts.save(id, &draft).is_ok() {
+                    return "its draft";
+                }
+            }
+            // Without a draft the composer is the only place left
+            if let Some(index) = self.channels.iter().position(|c| c.id == id) {
+                self.selected = index;
+                self.scroll = 0;
+            }
+        }
+
+        let message = join(self.editor.lines().join("\n"));
+        self.editor.set_message(&message);
+        self.save_draft();
+        "the composer"
+    }
+
+    /*
+     * Load the selected channel's draft into an empty composer.
+     */
+    fn restore_draft(&mut self) {
+        if !self.editor.message().is_empty() {
+            return;
+        }
+        let (Some(drafts), Some(channel)) = (&self.drafts, self.selected_channel()) else {
+            return;
+        };
+        if let Some(draft) = drafts.load(&channel.id) {
+            self.editor.set_message(&draft);
+            self.status = "Restored unsent draft".to_string();
+        }
+    }
+
+    /*
+     * Save the composer text as the selected channel's draft.
+     * Drafts are best effort, so failing to save never interrupts editing.
+     */
+    fn save_draft(&self) {
+        if let (Some(drafts), Some(channel)) = (&self.drafts, self.selected_channel()) {
+            drafts
+                .save(&channel.id, &self.editor.lines().join("\n"))
+                .ok();
+        }
+    }
+
+    /// Draw all panes for a terminal of the given size
+    pub fn draw(&self, stdout: &mut dyn Write, (width, height): (u16, u16)) -> io::Result<()> {
+        let sidebar_width = self.sidebar_width(width);
+        let composer_top = height.saturating_sub(COMPOSER_HEIGHT) + 1;
+
+        write!(stdout, "{}{}", cursor::Goto(1, 1), clear::All)?;
+
+        // Channel sidebar
+        let list_height = composer_top.saturating_sub(2) as usize;
+        let first = self.selected.saturating_sub(list_height.saturating_sub(1));
+        self.draw_title(stdout, 1, 1, "CHANNELS", self.focus == Focus::Channels)?;
+        for (row, channel) in self
+            .channels
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(list_height)
+        {
+            let label = truncate(&format!(" #{}", channel.name), sidebar_width as usize - 1);
+            write!(stdout, "{}", cursor::Goto(1, (row - first) as u16 + 2))?;
+            if row == self.selected {
+                write!(
+                    stdout,
+                    "{}{}{}{}{}",
+                    color::Fg(color::Black),
+                    color::Bg(color::White),
+                    label,
+                    color::Fg(color::Reset),
+                    color::Bg(color::Reset)
+                )?;
+            } else {
+                write!(stdout, "{}", label)?;
+            }
+        }
+        for row in 1..composer_top {
+            write!(stdout, "{}│", cursor::Goto(sidebar_width, row))?;
+        }
+
+        // History pane
+        let history_left = sidebar_width + 2;
+        let history_width = width.saturating_sub(history_left) as usize;
+        let title = match self.selected_channel() {
+            Some(channel) => format!("#{}", channel.name),
+            None => "HISTORY".to_string(),
+        };
+        self.draw_title(
+            stdout,
+            history_left,
+            1,
+            &title,
+            self.focus == Focus::History,
+        )?;
+        let lines = self.history_lines();
+        let visible = list_height;
+        let end = lines.len().saturating_sub(self.scroll.min(lines.len()));
+        let start = end.saturating_sub(visible);
+        for (row, line) in lines[start..end].iter().enumerate() {
+            write!(
+                stdout,
+                "{}{}{}",
+                cursor::Goto(history_left, row as u16 + 2),
+                truncate(line, history_width),
+                style::Reset
+            )?;
+        }
+
+        // Composer
+        write!(
+            stdout,
+            "{}{}",
+            cursor::Goto(1, composer_top),
+            "─".repeat(width as usize)
+        )?;
+        self.draw_title(
+            stdout,
+            1,
+            composer_top + 1,
+            USAGE_APP,
+            self.focus == Focus::Composer,
+        )?;
+        // Completion, search and formatting warnings of the composer take the status line
+        let hint = match self.focus {
+            Focus::Composer => self
+                .editor
+                .hint((width as usize).saturating_sub(USAGE_APP.len() + 2)),
+            _ => None,
+        };
+        let status = hint.as_deref().unwrap_or(&self.status);
+        if !status.is_empty() {
+            write!(stdout, " {}", status)?;
+        }
+        let composer_rows = COMPOSER_HEIGHT.saturating_sub(2) as usize;
+        let (cursor_line, cursor_col) = self.editor.cursor();
+        let first_line = (cursor_line + 1).saturating_sub(composer_rows);
+        for (row, line) in self
+            .editor
+            .lines()
+            .iter()
+            .enumerate()
+            .skip(first_line)
+            .take(composer_rows)
+        {
+            write!(
+                stdout,
+                "{}{}",
+                cursor::Goto(1, composer_top + 2 + (row - first_line) as u16),
+                truncate(line, width as usize)
+            )?;
+        }
+
+        if self.focus == Focus::Composer {
+            let line = &self.editor.lines()[cursor_line];
+            let col: usize = line
+                .chars()
+                .take(cursor_col)
+                .map(|c| c.width().unwrap_or(0))
+                .sum();
+            write!(
+                stdout,
+                "{}{}",
+                cursor::Show,
+                cursor::Goto(
+                    col as u16 + 1,
+                    composer_top + 2 + (cursor_line - first_line) as u16
+                )
+            )?;
+        } else {
+            write!(stdout, "{}", cursor::Hide)?;
+        }
+
+        stdout.flush()
+    }
+
+    fn draw_title(
+        &self,
+        stdout: &mut dyn Write,
+        x: u16,
+        y: u16,
+        title: &str,
+        focused: bool,
+    ) -> io::Result<()> {
+        write!(stdout, "{}", cursor::Goto(x, y))?;
+        if focused {
+            write!(
+                stdout,
+                "{}{}{}{}{}",
+                style::Bold,
+                color::Fg(color::Cyan),
+                title,
+                style::Reset,
+                color::Fg(color::Reset)
+            )
+        } else {
+            write!(stdout, "{}", title)
+        }
+    }
+
+    fn sidebar_width(&self, width: u16) -> u16 {
+        let longest = self
+            .channels
+            .iter()
+            .map(|c| c.name.chars().count())
+            .max()
+            .unwrap_or_default();
+        (longest as u16 + 4).clamp(12, (width / 3).max(12))
+    }
+
+    fn history_lines(&self) -> Vec<String> {
+        let Some(channel) = self.selected_channel() else {
+            return vec![];
+        };
+
+        match self.histories.get(&channel.id) {
+            Some(History::Loaded(messages)) if messages.is_empty() => {
+                vec!["No messages".to_string()]
+            }
+            Some(History::Loaded(messages)) => MessageViewer::new(&channel.name).lines(messages),
+            Some(History::Failed(err)) => vec![format!("Failed to load messages: {}", err)],
+            Some(History::Loading) | None => vec!["Loading...".to_string()],
+        }
+    }
+
+    /// Run the interface until the user quits
+    /// History loads and posts run in the background so the interface stays responsive
+    pub async fn run<S, R, W>(
+        mut self,
+        slack_client: S,
+        stdin: R,
+        stdout: &mut W,
+        size: fn() -> (u16, u16),
+    ) -> Result<()>
+    where
+        S: SlackApi,
+        R: Read + Send + 'static,
+        W: Write,
+    {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let keys = tx.clone();
+        thread::spawn(move || {
+            for input in inputs(stdin).map_while(|input| input.ok()) {
+                let event = match input {
+                    Input::Key(key) => Event::Key(key),
+                    Input::Paste(text) => Event::Paste(text),
+                };
+                if keys.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+
+        write!(stdout, "{}", ENABLE_BRACKETED_PASTE)?;
+
+        let mut pending = self.load_selected().into_iter().collect::<Vec<_>>();
+        loop {
+            for action in pending.drain(..) {
+                match action {
+                    Action::Quit => {
+                        write!(stdout, "{}{}", DISABLE_BRACKETED_PASTE, cursor::Show)?;
+                        return Ok(());
+                    }
+                    Action::LoadHistory(id) => {
+                        let (client, tx) = (slack_client.clone(), tx.clone());
+                        tokio::spawn(async move {
+                            let history = client
+                                .get_messages(&id, HISTORY_LIMIT, &HistoryRange::default())
+                                .await
+                                .map(|messages| messages.messages);
+                            let _ = tx.send(Event::History(id, history));
+                        });
+                    }
+                    Action::Post(id, text) => {
+                        let (client, tx) = (slack_client.clone(), tx.clone());
+                        tokio::spawn(async move {
+                            // Later parts are only posted if the earlier ones were
+                            let mut posted = Ok(());
+                            for (index, part) in
+                                split_message(&text, MAX_MESSAGE_LENGTH).iter().enumerate()
+                            {
+                                if let Err(err) = client.post_message(&id, part).await {
+                                    posted = Err((index, err));
+                                    break;
+                                }
+                            }
+                            let _ = tx.send(Event::Posted(id, posted));
+                        });
+                    }
+                }
+            }
+
+            self.draw(stdout, size())?;
+
+            let Some(event) = rx.recv().await else {
+                return Ok(());
+            };
+            match event {
+                Event::Key(key) => pending.extend(self.handle_key(key)),
+                Event::Paste(text) => self.handle_paste(&text),
+                Event::History(id, history) => self.set_history(&id, history),
+                Event::Posted(id, Ok(())) => {
+                    self.posting.remove(&id);
+                    self.status = "[Success]".to_string();
+                    self.histories.remove(&id);
+                    pending.extend(self.load_selected());
+                }
+                Event::Posted(id, Err((posted, err))) => self.post_failed(&id, posted, err),
+            }
+        }
+    }
+}
+
+/*
+ * Cut a line to the given display width, keeping escape sequences intact.
+ */
+fn truncate(line: &str, width: usize) -> String {
+    let mut truncated = String::new();
+    let mut used = 0;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            truncated.push(c);
+            // Copy the escape sequence up to its final letter
+            for c in chars.by_ref() {
+                truncated.push(c);
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width > width {
+            break;
+        }
+        used += char_width;
+        truncated.push(c);
+    }
+
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slack::FakeSlack;
+    use serial_test::serial;
+    use std::path::PathBuf;
+
+    fn channels() -> Vec<SlackChannel> {
+        vec![
+            SlackChannel {
+                id: "C001".to_string(),
+                name: "general".to_string(),
+            },
+            SlackChannel {
+                id: "C002".to_string(),
+                name: "random".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn tab_cycles_focus() {
+        let mut app = App::new(channels());
+        assert_eq!(app.focus(), Focus::Channels);
+        app.handle_key(Key::Char('\t'));
+        assert_eq!(app.focus(), Focus::History);
+        app.handle_key(Key::Char('\t'));
+        assert_eq!(app.focus(), Focus::Composer);
+        app.handle_key(Key::BackTab);
+        assert_eq!(app.focus(), Focus::History);
+    }
+
+    #[test]
+    fn tab_completes_in_composer() {
+        let completer = Completer::new(&Vec::new().into(), &channels().into(), Vec::new());
+        let mut app = App::new(channels()).with_completer(completer);
+        app.handle_key(Key::Char('\n'));
+        for c in "#gen".chars() {
+            app.handle_key(Key::Char(c));
+        }
+        app.handle_key(Key::Char('\t'));
+        assert_eq!(app.focus(), Focus::Composer);
+        assert_eq!(app.editor.lines()[0], "#general ");
+
+        app.handle_key(Key::Char('\t'));
+        assert_eq!(app.focus(), Focus::Channels);
+    }
+
+    #[test]
+    fn composer_keeps_drafts_per_channel() {
+        let dir = std::env::temp_dir().join(format!("rslack-app-drafts-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let drafts = DraftStore::new(dir);
+        drafts.save("C002", "for random").unwrap();
+
+        let mut app = App::new(channels()).with_drafts(drafts.clone());
+        app.handle_key(Key::Char('\n'));
+        app.handle_key(Key::Char('a'));
+        assert_eq!(drafts.load("C001"), Some("a".to_string()));
+
+        app.handle_key(Key::Ctrl('p'));
+        app.handle_key(Key::Esc);
+        app.handle_key(Key::Char('j'));
+        app.handle_key(Key::Char('\n'));
+        assert_eq!(app.editor.message(), "for random");
+        assert_eq!(app.status, "Restored unsent draft");
+    }
+
+    #[test]
+    fn paste_goes_to_composer_only() {
+        let mut app = App::new(channels());
+        app.handle_paste("q\njj");
+        assert_eq!(app.focus(), Focus::Channels);
+        assert_eq!(app.selected_channel().unwrap().name, "general");
+
+        app.handle_key(Key::Char('\n'));
+        app.handle_paste("q\njj");
+        assert_eq!(app.editor.lines(), ["q", "jj"]);
+    }
+
+    #[test]
+    fn moving_selection_loads_history_once() {
+        let mut app = App::new(channels());
+        assert_eq!(
+            app.load_selected(),
+            Some(Action::LoadHistory("C001".to_string()))
+        );
+        assert_eq!(
+            app.handle_key(Key::Char('j')),
+            Some(Action::LoadHistory("C002".to_string()))
+        );
+        assert_eq!(app.handle_key(Key::Char('k')), None);
+        assert_eq!(app.selected_channel().unwrap().name, "general");
+    }
+
+    #[test]
+    fn composer_posts_to_selected_channel() {
+        let mut app = App::new(channels());
+        app.select("random");
+        app.handle_key(Key::Char('\n'));
+        assert_eq!(app.focus(), Focus::Composer);
+        app.handle_key(Key::Char('h'));
+        app.handle_key(Key::Char('i'));
+        assert_eq!(
+            app.handle_key(Key::Ctrl('p')),
+            Some(Action::Post("C002".to_string(), "hi".to_string()))
+        );
+        assert!(app.editor.message().is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn composer_asks_again_before_posting_to_protected_channel() {
+        let config = Config::new(Some(PathBuf::from(
+            "tests/fixtures/config/config.test.options",
+        )))
+        .unwrap();
+        let mut app = App::new(channels()).with_post_checks(&config);
+        app.handle_key(Key::Char('\n'));
+        app.handle_key(Key::Char('h'));
+        app.handle_key(Key::Char('i'));
+
+        assert_eq!(app.handle_key(Key::Ctrl('p')), None);
+        assert!(app.status.starts_with("#general is a protected channel."));
+        assert_eq!(app.editor.message(), "hi");
+
+        // Changing the message asks again
+        app.handle_key(Key::Char('!'));
+        assert_eq!(app.handle_key(Key::Ctrl('p')), None);
+        assert_eq!(
+            app.handle_key(Key::Ctrl('p')),
+            Some(Action::Post("C001".to_string(), "hi!".to_string()))
+        );
+
+        app.handle_key(Key::Esc);
+        app.handle_key(Key::Char('j'));
+        app.handle_key(Key::Char('\n'));
+        app.handle_key(Key::Char('x'));
+        assert_eq!(
+            app.handle_key(Key::Ctrl('p')),
+            Some(Action::Post("C002".to_string(), "x".to_string()))
+        );
+    }
+
+    #[test]
+    fn draw_shows_status() {
+        let mut app = App::new(channels());
+        app.set_status("Channel 'C404' not found");
+        let mut stdout = Vec::new();
+        app.draw(&mut stdout, (80, 24)).unwrap();
+        assert!(String::from_utf8(stdout)
+            .unwrap()
+            .contains("Channel 'C404' not found"));
+    }
+
+    #[test]
+    fn draw_shows_loaded_history() {
+        let mut app = App::new(channels());
+        app.set_history(
+            "C001",
+            Ok(vec![SlackMessage {
+                text: "hello from history".to_string(),
+                user: Some("U001".to_string()),
+                ts: "1609459200.000000".to_string(),
+                thread_ts: None,
+                reply_count: None,
+            }]),
+        );
+        let mut stdout = Vec::new();
+        app.draw(&mut stdout, (80, 24)).unwrap();
+        let screen = String::from_utf8(stdout).unwrap();
+        assert!(screen.contains("#general"));
+        assert!(screen.contains("hello from history"));
+    }
+
+    #[tokio::test]
+    async fn run_loads_history_and_posts_with_fake_slack() {
+        let slack = FakeSlack::new()
+            .with_channel("C001", "general")
+            .with_message("C001", "U001", "earlier message");
+        let app = App::new(slack.get_channels().await.unwrap().channels);
+
+        // Open the composer, type "hi" and post it with Ctrl-P
+        let (reader, mut writer) = io::pipe().unwrap();
+        writer.write_all(b"\nhi\x10").unwrap();
+        let quit_after_post = async {
+            while slack.messages("C001").len() < 2 {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+            writer.write_all(b"\x03").unwrap();
+        };
+
+        let mut stdout = Vec::new();
+        let (result, ()) = tokio::join!(
+            app.run(slack.clone(), reader, &mut stdout, || (80, 24)),
+            quit_after_post
+        );
+        result.unwrap();
+
+        assert_eq!(slack.messages("C001")[1].text, "hi");
+        let screen = String::from_utf8_lossy(&stdout);
+        assert!(screen.contains("earlier message"));
+        assert!(screen.contains("[Success]"));
+    }
+
+    #[tokio::test]
+    async fn run_splits_overlong_posts() {
+        let slack = FakeSlack::new().with_channel("C001", "general");
+        let app = App::new(slack.get_channels().await.unwrap().channels);
+
+        // Open the composer, paste a message over the limit and post it with Ctrl-P
+        let (reader, mut writer) = io::pipe().unwrap();
+        let text = "a ".repeat(MAX_MESSAGE_LENGTH / 2 + 10);
+        writer
+            .write_all(format!("\n\x1b[200~{}\x1b[201~\x10", text.trim_end()).as_bytes())
+            .unwrap();
+        let quit_after_post = async {
+            while slack.messages("C001").len() < 2 {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+            writer.write_all(b"\x03").unwrap();
+        };
+
+        let mut stdout = Vec::new();
+        let (result, ()) = tokio::join!(
+            app.run(slack.clone(), reader, &mut stdout, || (80, 24)),
+            quit_after_post
+        );
+        result.unwrap();
+
+        let messages = slack.messages("C001");
+        assert_eq!(messages.len(), 2);
+        assert!(messages
+            .iter()
+            .all(|message| message.text.chars().count() <= MAX_MESSAGE_LENGTH));
+    }
+
+    #[tokio::test]
+    async fn run_restores_text_when_post_fails() {
+        let slack = FakeSlack::new()
+            .with_channel("C001", "general")
+            .with_post_error("C001", "is_archived");
+        let dir = std::env::temp_dir().join(format!("rslack-app-failed-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let drafts = DraftStore::new(dir);
+        let app =
+            App::new(slack.get_channels().await.unwrap().channels).with_drafts(drafts.clone());
+
+        let (reader, mut writer) = io::pipe().unwrap();
+        writer.write_all(b"\nhi\x10").unwrap();
+        let quit_after_failure = async {
+            while drafts.load("C001").is_none() {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+            writer.write_all(b"\x03").unwrap();
+        };
+
+        let mut stdout = Vec::new();
+        let (result, ()) = tokio::join!(
+            app.run(slack.clone(), reader, &mut stdout, || (80, 24)),
+            quit_after_failure
+        );
+        result.unwrap();
+
+        assert!(slack.messages("C001").is_empty());
+        assert_eq!(drafts.load("C001"), Some("hi".to_string()));
+        assert!(String::from_utf8_lossy(&stdout).contains("[Failure] #general: is_archived"));
+    }
+
+    #[tokio::test]
+    async fn failed_posts_keep_only_unsent_parts() {
+        let text = format!("{}\n{}", "a".repeat(MAX_MESSAGE_LENGTH), "rest");
+        let mut app = App::new(channels());
+        app.handle_key(Key::Char('\n'));
+        app.handle_paste(&text);
+        app.handle_key(Key::Ctrl('p'));
+        assert!(app.editor.message().is_empty());
+
+        // A second post has to wait for the first one
+        app.handle_key(Key::Char('x'));
+        assert_eq!(app.handle_key(Key::Ctrl('p')), None);
+        assert_eq!(app.status, "Still posting to #general...");
+
+        // The first part was posted, so only the second one comes back
+        app.post_failed("C001", 1, anyhow::anyhow!("is_archived"));
+        assert_eq!(app.editor.lines(), ["rest", "x"]);
+        assert!(app.status.starts_with("[Failure] #general: is_archived."));
+
+        // Parts that failed because Slack could not be reached are queued
+        let path = std::env::temp_dir().join(format!("rslack-app-outbox-{}", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        app = app.with_outbox(Outbox::new(path.clone()).unwrap());
+        app.editor.set_message(&text);
+        app.handle_key(Key::Ctrl('p'));
+        let unreachable = reqwest::get("http://127.0.0.1:1").await.unwrap_err();
+        app.post_failed("C001", 1, unreachable.into());
+
+        let outbox = Outbox::new(path.clone()).unwrap();
+        let queued: Vec<&str> = outbox.entries().iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(queued, ["rest"]);
+        assert!(app.editor.message().is_empty());
+        assert!(app.status.starts_with("[Queued] #general:"));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn truncate_keeps_escape_sequences() {
+        assert_eq!(truncate("hello", 3), "hel");
+        assert_eq!(truncate("こんにちは", 5), "こん");
+        assert_eq!(truncate("\x1b[1mbold\x1b[0m", 2), "\x1b[1mbo");
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::io::{Read, Write};
 
 use anyhow::Result;
@@ -5,19 +6,29 @@ use rpos::table::Table as RposTable;
 use rpos::WrapMode;
 use termion::event::Key;
 use termion::input::TermRead;
+use unicode_width::UnicodeWidthStr;
 
 use super::Table;
 
+const ALIAS_ARROW: &str = " → ";
+
 /// Interactive channel selector with vim-like navigation
 pub struct ChannelSelector {
     table: Table,
     channel_names: Vec<String>,
+    /// (label shown in the table, channel the alias points to)
+    aliases: Vec<(String, String)>,
+    multi_select: bool,
+    /// Width in columns, or the terminal width if None
+    width: Option<usize>,
 }
 
 /// Result of channel selection
 pub enum SelectionResult {
     /// User selected a channel
     Selected(String),
+    /// User toggled several channels with Space (multi-select only)
+    SelectedMany(Vec<String>),
     /// User cancelled the selection (q or Ctrl-C)
     Cancelled,
 }
@@ -28,6 +39,77 @@ impl ChannelSelector {
         Self {
             table,
             channel_names,
+            aliases: Vec::new(),
+            multi_select: false,
+            width: None,
+        }
+    }
+
+    /// Allow toggling several channels with Space before confirming with Enter
+    pub fn with_multi_select(mut self, multi_select: bool) -> Self {
+        self.multi_select = multi_select;
+        self.table = self.table.with_multi_select(multi_select);
+        self
+    }
+
+    /// Lay out the table for a fixed width instead of the terminal width
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = Some(width);
+        self.table = self.table.with_width(width);
+        self
+    }
+
+    /// Show channel aliases as additional entries after the channels.
+    /// Selecting an alias returns the channel it points to.
+    pub fn with_aliases(mut self, aliases: &BTreeMap<String, String>) -> Self {
+        self.aliases = aliases
+            .iter()
+            .map(|(alias, target)| {
+                (
+                    format!("{}{}{}", alias, ALIAS_ARROW, target),
+                    target.clone(),
+                )
+            })
+            .collect();
+
+        let labels = self
+            .channel_names
+            .iter()
+            .cloned()
+            .chain(self.aliases.iter().map(|(label, _)| label.clone()))
+            .collect::<Vec<_>>();
+        let max_col_size = labels
+            .iter()
+            .map(|label| UnicodeWidthStr::width(label.as_str()))
+            .max()
+            .unwrap_or_default()
+            .max(self.table.max_col_size());
+        self.table = Table::new("CHANNELS".to_string(), labels, max_col_size)
+            .with_multi_select(self.multi_select);
+        if let Some(width) = self.width {
+            self.table = self.table.with_width(width);
+        }
+        self
+    }
+
+    /*
+     * Map a selected table cell back to a channel name.
+     */
+    fn resolve_label(&self, label: &str) -> String {
+        self.aliases
+            .iter()
+            .find(|(alias_label, _)| alias_label == label)
+            .map(|(_, target)| target.clone())
+            .unwrap_or_else(|| label.to_string())
+    }
+
+    /// Toggle a table cell in the list of marked cells
+    fn toggle(marked: &mut Vec<String>, label: &str) {
+        match marked.iter().position(|m| m == label) {
+            Some(pos) => {
+                marked.remove(pos);
+            }
+            None => marked.push(label.to_string()),
         }
     }
 
@@ -53,12 +135,23 @@ impl ChannelSelector {
             .cursor;
 
         let mut selected = chunked_data[cursor.current().0][cursor.current().1].to_string();
-        self.table.draw(stdout, &selected);
+        let mut marked: Vec<String> = Vec::new();
+        self.table.draw_marked(stdout, &selected, &marked);
 
         for c in stdin.keys() {
             match c? {
                 Key::Char('q') | Key::Ctrl('c') => return Ok(SelectionResult::Cancelled),
-                Key::Char('\n') => return Ok(SelectionResult::Selected(selected.to_string())),
+                Key::Char('\n') if !marked.is_empty() => {
+                    return Ok(SelectionResult::SelectedMany(
+                        marked.iter().map(|m| self.resolve_label(m)).collect(),
+                    ))
+                }
+                Key::Char('\n') => {
+                    return Ok(SelectionResult::Selected(self.resolve_label(&selected)))
+                }
+                Key::Char(' ') if self.multi_select => {
+                    Self::toggle(&mut marked, &selected);
+                }
                 Key::Left | Key::Char('h') => {
                     cursor.left();
                 }
@@ -93,21 +186,28 @@ impl ChannelSelector {
             }
 
             selected = chunked_data[cursor.current().0][cursor.current().1].to_string();
-            self.table.draw(stdout, &selected);
+            self.table.draw_marked(stdout, &selected, &marked);
         }
 
-        Ok(SelectionResult::Selected(selected))
+        Ok(SelectionResult::Selected(self.resolve_label(&selected)))
     }
 
     /// Draw the table with the given channel selected
     pub fn draw<W: Write>(&self, stdout: &mut W, selected: &str) {
         self.table.draw(stdout, selected);
     }
+
+    /// Draw the table with all of the given channels highlighted
+    pub fn draw_many<W: Write>(&self, stdout: &mut W, selected: &[String]) {
+        let first = selected.first().map(String::as_str).unwrap_or_default();
+        self.table.draw_marked(stdout, first, selected);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::console::screen::{Color, Screen};
 
     #[test]
     fn selector_new_creates_instance() {
@@ -138,4 +238,70 @@ mod tests {
         assert!(!selector.needs_selection("general"));
         assert!(!selector.needs_selection("random"));
     }
+
+    #[test]
+    fn with_aliases_resolves_selected_alias() {
+        let channels = vec!["general".to_string(), "team-alerts".to_string()];
+        let aliases = BTreeMap::from([("alerts".to_string(), "team-alerts".to_string())]);
+        let selector = ChannelSelector::new(channels, 11).with_aliases(&aliases);
+        assert_eq!(selector.aliases.len(), 1);
+        assert_eq!(
+            selector.resolve_label("alerts → team-alerts"),
+            "team-alerts"
+        );
+        assert_eq!(selector.resolve_label("general"), "general");
+        assert!(!selector.needs_selection("team-alerts"));
+    }
+
+    #[test]
+    fn run_returns_toggled_channels_in_multi_select() {
+        let channels = vec!["general".to_string(), "random".to_string()];
+        let selector = ChannelSelector::new(channels, 10).with_multi_select(true);
+        let mut stdout = Vec::new();
+        // toggle general, move right, toggle random, confirm
+        match selector.run(&b" l \n"[..], &mut stdout).unwrap() {
+            SelectionResult::SelectedMany(selected) => {
+                assert_eq!(selected, vec!["general".to_string(), "random".to_string()])
+            }
+            _ => panic!("expected multiple channels"),
+        }
+    }
+
+    #[test]
+    fn run_ignores_space_without_multi_select() {
+        let channels = vec!["general".to_string(), "random".to_string()];
+        let selector = ChannelSelector::new(channels, 10);
+        let mut stdout = Vec::new();
+        match selector.run(&b" \n"[..], &mut stdout).unwrap() {
+            SelectionResult::Selected(selected) => assert_eq!(selected, "general"),
+            _ => panic!("expected a single channel"),
+        }
+    }
+
+    #[test]
+    fn run_draws_moves_and_marks() {
+        let channels = ["general", "random", "deploys", "alerts"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        let aliases = BTreeMap::from([("ops".to_string(), "deploys".to_string())]);
+        let selector = ChannelSelector::new(channels, 7)
+            .with_multi_select(true)
+            .with_aliases(&aliases)
+            .with_width(40);
+        let mut screen = Screen::new(40, 12);
+        // toggle general, move down and right, then run out of input
+        match selector.run(&b" jl"[..], &mut screen).unwrap() {
+            SelectionResult::Selected(selected) => assert_eq!(selected, "alerts"),
+            _ => panic!("expected the channel under the cursor"),
+        }
+        screen.assert_snapshot("channel_selector");
+
+        let (x, y) = screen.find("alerts").unwrap();
+        assert_eq!(screen.style_at(x, y).bg, Some(Color::Indexed(7)));
+        let (x, y) = screen.find("general").unwrap();
+        assert_eq!(screen.style_at(x, y).bg, Some(Color::Indexed(2)));
+        let (x, y) = screen.find("ops → deploys").unwrap();
+        assert_eq!(screen.style_at(x, y).bg, None);
+    }
 }
@@ -0,0 +1,396 @@
+use crate::slack::{SlackChannels, SlackUsers};
+
+/// Standard emoji names offered in addition to the workspace's custom emoji
+const STANDARD_EMOJI: &[&str] = &[
+    "+1",
+    "-1",
+    "100",
+    "alarm_clock",
+    "angry",
+    "arrow_down",
+    "arrow_left",
+    "arrow_right",
+    "arrow_up",
+    "bangbang",
+    "beers",
+    "bell",
+    "blush",
+    "boom",
+    "bow",
+    "bug",
+    "bulb",
+    "calendar",
+    "clap",
+    "coffee",
+    "confused",
+    "construction",
+    "cry",
+    "dart",
+    "disappointed",
+    "eyes",
+    "facepalm",
+    "fire",
+    "grimacing",
+    "grin",
+    "grinning",
+    "heart",
+    "heavy_check_mark",
+    "heavy_plus_sign",
+    "hourglass",
+    "hugging_face",
+    "information_source",
+    "joy",
+    "key",
+    "laughing",
+    "link",
+    "lock",
+    "mag",
+    "memo",
+    "muscle",
+    "no_entry",
+    "ok",
+    "ok_hand",
+    "pencil",
+    "pray",
+    "question",
+    "raised_hands",
+    "recycle",
+    "red_circle",
+    "relieved",
+    "rocket",
+    "rotating_light",
+    "scream",
+    "see_no_evil",
+    "shipit",
+    "shrug",
+    "simple_smile",
+    "slightly_smiling_face",
+    "smile",
+    "smiley",
+    "smirk",
+    "sob",
+    "sparkles",
+    "star",
+    "sunglasses",
+    "sweat_smile",
+    "tada",
+    "thinking_face",
+    "thumbsdown",
+    "thumbsup",
+    "tired_face",
+    "trophy",
+    "upside_down_face",
+    "v",
+    "warning",
+    "wave",
+    "white_check_mark",
+    "wink",
+    "wrench",
+    "x",
+    "zap",
+    "zzz",
+];
+
+/// Special mentions that notify a whole channel
+const SPECIAL_MENTIONS: &[&str] = &["channel", "here", "everyone"];
+
+/// Candidates for @mention, #channel and :emoji: completion
+#[derive(Default)]
+pub struct Completer {
+    /// (user name, user ID)
+    users: Vec<(String, String)>,
+    /// (channel name, channel ID)
+    channels: Vec<(String, String)>,
+    emoji: Vec<String>,
+}
+
+impl Completer {
+    pub fn new(users: &SlackUsers, channels: &SlackChannels, custom_emoji: Vec<String>) -> Self {
+        let mut emoji = custom_emoji;
+        emoji.extend(STANDARD_EMOJI.iter().map(|name| name.to_string()));
+        emoji.sort();
+        emoji.dedup();
+
+        Self {
+            users: users
+                .users
+                .iter()
+                .map(|user| (user.name.clone(), user.id.clone()))
+                .collect(),
+            channels: channels
+                .channels
+                .iter()
+                .map(|channel| (channel.name.clone(), channel.id.clone()))
+                .collect(),
+            emoji,
+        }
+    }
+
+    /// Check if the char starts a completable word
+    pub fn is_trigger(c: char) -> bool {
+        matches!(c, '@' | '#' | ':')
+    }
+
+    /// Completed words for a word starting with a trigger char, e.g. `@al` -> [`@alice`]
+    pub fn candidates(&self, word: &str) -> Vec<String> {
+        let mut chars = word.chars();
+        let (Some(trigger), prefix) = (chars.next(), chars.as_str()) else {
+            return vec![];
+        };
+
+        let matching = |names: &mut dyn Iterator<Item = &str>| -> Vec<String> {
+            let mut names = names
+                .filter(|name| name.starts_with(prefix))
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+
+        match trigger {
+            '@' => matching(
+                &mut SPECIAL_MENTIONS
+                    .iter()
+                    .copied()
+                    .chain(self.users.iter().map(|(name, _)| name.as_str())),
+            )
+            .into_iter()
+            .map(|name| format!("@{}", name))
+            .collect(),
+            '#' => matching(&mut self.channels.iter().map(|(name, _)| name.as_str()))
+                .into_iter()
+                .map(|name| format!("#{}", name))
+                .collect(),
+            ':' if !prefix.is_empty() => matching(&mut self.emoji.iter().map(String::as_str))
+                .into_iter()
+                .map(|name| format!(":{}:", name))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Name of a user by ID
+    pub fn user_name(&self, id: &str) -> Option<&str> {
+        self.users
+            .iter()
+            .find(|(_, user_id)| user_id == id)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Name of a channel by ID
+    pub fn channel_name(&self, id: &str) -> Option<&str> {
+        self.channels
+            .iter()
+            .find(|(_, channel_id)| channel_id == id)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Convert `@user`, `#channel`, `@here`, `@channel` and `@everyone`
+    /// to the `<@U…>`, `<#C…>` and `<!here>` forms Slack notifies on.
+    /// Text in `code` spans and ``` blocks is left as typed.
+    pub fn encode(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut encoded = String::with_capacity(text.len());
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            if let Some(end) = code_span_end(&chars, i) {
+                encoded.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            let at_boundary = i == 0 || !is_name_char(chars[i - 1]);
+            if (c == '@' || c == '#') && at_boundary {
+                let run: String = chars[i + 1..]
+                    .iter()
+                    .take_while(|c| is_name_char(**c))
+                    .collect();
+                if let Some((len, replacement)) = self.longest_match(c, &run) {
+                    encoded.push_str(&replacement);
+                    i += 1 + len;
+                    continue;
+                }
+            }
+
+            encoded.push(c);
+            i += 1;
+        }
+
+        encoded
+    }
+
+    /// Check if `encode` needs workspace users for the text, i.e. if it has
+    /// an `@name` outside code other than `@here`, `@channel` and `@everyone`
+    pub fn mentions_users(text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+
+        while i < chars.len() {
+            if let Some(end) = code_span_end(&chars, i) {
+                i = end;
+                continue;
+            }
+            let at_boundary = i == 0 || !is_name_char(chars[i - 1]);
+            if chars[i] == '@' && at_boundary {
+                let run: String = chars[i + 1..]
+                    .iter()
+                    .take_while(|c| is_name_char(**c))
+                    .collect();
+                if !run.is_empty() && !SPECIAL_MENTIONS.contains(&run.as_str()) {
+                    return true;
+                }
+            }
+            i += 1;
+        }
+
+        false
+    }
+
+    /*
+     * Longest known name at the start of `run`, as (char length, encoded mention).
+     */
+    fn longest_match(&self, trigger: char, run: &str) -> Option<(usize, String)> {
+        let names: Vec<(&str, String)> = match trigger {
+            '@' => SPECIAL_MENTIONS
+                .iter()
+                .map(|name| (*name, format!("<!{}>", name)))
+                .chain(
+                    self.users
+                        .iter()
+                        .map(|(name, id)| (name.as_str(), format!("<@{}>", id))),
+                )
+                .collect(),
+            _ => self
+                .channels
+                .iter()
+                .map(|(name, id)| (name.as_str(), format!("<#{}>", id)))
+                .collect(),
+        };
+
+        names
+            .into_iter()
+            .filter(|(name, _)| {
+                run.starts_with(name)
+                    && !run[name.len()..]
+                        .chars()
+                        .next()
+                        .is_some_and(|next| next.is_alphanumeric() || next == '_')
+            })
+            .max_by_key(|(name, _)| name.len())
+            .map(|(name, encoded)| (name.chars().count(), encoded))
+    }
+}
+
+/*
+ * End (exclusive) of the code block or inline code span starting at `start`, if it is closed.
+ * Inline code ends at the line end like in Slack.
+ */
+fn code_span_end(chars: &[char], start: usize) -> Option<usize> {
+    const FENCE: [char; 3] = ['`'; 3];
+
+    if chars[start..].starts_with(&FENCE) {
+        let body = start + FENCE.len();
+        return (body..chars.len())
+            .find(|&i| chars[i..].starts_with(&FENCE))
+            .map(|i| i + FENCE.len());
+    }
+    if chars[start] == '`' {
+        return chars[start + 1..]
+            .iter()
+            .take_while(|c| **c != '\n')
+            .position(|c| *c == '`')
+            .map(|i| start + 1 + i + 1);
+    }
+    None
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '.' | '_' | '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slack::{SlackChannel, SlackUser};
+
+    fn completer() -> Completer {
+        let users = SlackUsers::from(vec![
+            SlackUser {
+                id: "U001".to_string(),
+                name: "alice".to_string(),
+                real_name: None,
+                profile: None,
+            },
+            SlackUser {
+                id: "U002".to_string(),
+                name: "alice.smith".to_string(),
+                real_name: None,
+                profile: None,
+            },
+        ]);
+        let channels = SlackChannels::from(vec![SlackChannel {
+            id: "C001".to_string(),
+            name: "general".to_string(),
+        }]);
+        Completer::new(&users, &channels, vec!["partyparrot".to_string()])
+    }
+
+    #[test]
+    fn candidates_for_users_channels_and_emoji() {
+        let completer = completer();
+        assert_eq!(
+            completer.candidates("@al"),
+            vec!["@alice".to_string(), "@alice.smith".to_string()]
+        );
+        assert_eq!(completer.candidates("@he"), vec!["@here".to_string()]);
+        assert_eq!(completer.candidates("#gen"), vec!["#general".to_string()]);
+        assert_eq!(
+            completer.candidates(":party"),
+            vec![":partyparrot:".to_string()]
+        );
+        assert_eq!(completer.candidates(":tad"), vec![":tada:".to_string()]);
+        assert!(completer.candidates(":").is_empty());
+    }
+
+    #[test]
+    fn encode_mentions() {
+        let completer = completer();
+        assert_eq!(
+            completer.encode("@alice.smith and @alice, see #general. @here"),
+            "<@U002> and <@U001>, see <#C001>. <!here>"
+        );
+    }
+
+    #[test]
+    fn encode_leaves_unknown_names_and_emails() {
+        let completer = completer();
+        assert_eq!(
+            completer.encode("mail bob@alice.com or @bob #1 @alicex"),
+            "mail bob@alice.com or @bob #1 @alicex"
+        );
+    }
+
+    #[test]
+    fn encode_skips_code() {
+        let completer = completer();
+        assert_eq!(
+            completer.encode("@alice `@alice #general` ```\n@here\n``` @here"),
+            "<@U001> `@alice #general` ```\n@here\n``` <!here>"
+        );
+        // Unclosed backticks do not start code
+        assert_eq!(completer.encode("`@alice\n@bob`"), "`<@U001>\n@bob`");
+    }
+
+    #[test]
+    fn mentions_users_ignores_code_and_broadcasts() {
+        assert!(Completer::mentions_users("hi @alice"));
+        assert!(Completer::mentions_users("@here and @bob"));
+        assert!(!Completer::mentions_users(
+            "@here @channel @everyone #general"
+        ));
+        assert!(!Completer::mentions_users(
+            "`@alice` ```\n@bob\n``` mail@example.com @"
+        ));
+    }
+}
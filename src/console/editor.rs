@@ -2,9 +2,24 @@ use std::io::{Read, Write};
 
 use anyhow::Result;
 use termion::event::Key;
-use termion::input::TermRead;
+use unicode_width::UnicodeWidthChar;
 
-const USAGE_EDITOR: &str = "(post: ctrl-p / exit: ctrl-c)";
+mod vi;
+
+use super::input::{inputs, Input, DISABLE_BRACKETED_PASTE, ENABLE_BRACKETED_PASTE};
+use super::mrkdwn;
+use super::table::term_size;
+use super::Completer;
+use crate::draft::DraftStore;
+use crate::slack::MAX_MESSAGE_LENGTH;
+use vi::{Vi, ViMode};
+
+const USAGE_EDITOR: &str =
+    "(post: ctrl-p / preview: ctrl-o / history: up, ctrl-r / $EDITOR: ctrl-x ctrl-e / exit: ctrl-c)";
+/// First terminal row of the message
+const MESSAGE_TOP: u16 = 3;
+/// Number of edits that can be undone
+const UNDO_LIMIT: usize = 100;
 
 /// Result of message editing
 pub enum EditorResult {
@@ -12,11 +27,77 @@ pub enum EditorResult {
     Submitted(String),
     /// User cancelled the editor (Ctrl-C)
     Cancelled,
+    /// User asked to edit the message in $VISUAL / $EDITOR (Ctrl-X Ctrl-E)
+    External(String),
 }
 
 pub struct Editor {
     buffer: Vec<String>,
     cursor_line: usize,
+    /// Cursor position in the current line, in chars
+    cursor_col: usize,
+    /// Wrap width in columns, or the terminal width if None
+    width: Option<usize>,
+    /// Ctrl-X was pressed and the next key completes a key sequence
+    ctrl_x: bool,
+    completer: Option<Completer>,
+    /// Completion in progress, cycled by repeated Tab presses
+    completion: Option<Completion>,
+    /// Store and key the buffer is saved to while editing
+    draft: Option<(DraftStore, String)>,
+    /// Draft found when the editor was opened, offered once by `run`
+    restorable: Option<String>,
+    /// Last text written to the draft store
+    saved_draft: String,
+    /// Sent messages, newest first
+    history: Vec<String>,
+    /// Index of the sent message recalled with Up or Ctrl-R
+    recall: Option<usize>,
+    search: Option<Search>,
+    /// Buffer states before each edit, most recent last
+    undo: Vec<Snapshot>,
+    /// Buffer states undone with Ctrl-Z, most recent last
+    redo: Vec<Snapshot>,
+    /// The last key typed a word character, so the next one extends the same edit
+    typing: bool,
+    /// vi keybindings, if enabled
+    vi: Option<Vi>,
+    /// Show the formatted message below the buffer
+    preview: bool,
+    /// Message whose formatting warnings were shown by Ctrl-P
+    warned: Option<String>,
+}
+
+/// Buffer and cursor saved for undo and redo
+struct Snapshot {
+    buffer: Vec<String>,
+    cursor: (usize, usize),
+}
+
+/// Reverse incremental search through sent messages
+struct Search {
+    query: String,
+    /// Index of the sent message matching the query
+    index: Option<usize>,
+    /// No older sent message matches the query
+    failed: bool,
+    /// Buffer before the search, restored when it is aborted
+    original: String,
+}
+
+/// Candidates for the word being completed
+struct Completion {
+    /// Char column where the completed word starts
+    start: usize,
+    candidates: Vec<String>,
+    index: usize,
+}
+
+/// Buffer lines soft-wrapped to the screen width
+struct Layout {
+    rows: Vec<String>,
+    /// Cursor position as (row, display column)
+    cursor: (usize, usize),
 }
 
 impl Editor {
@@ -24,7 +105,67 @@ impl Editor {
         Editor {
             buffer: vec![String::new()],
             cursor_line: 0,
+            cursor_col: 0,
+            width: None,
+            ctrl_x: false,
+            completer: None,
+            completion: None,
+            draft: None,
+            restorable: None,
+            saved_draft: String::new(),
+            history: Vec::new(),
+            recall: None,
+            search: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            typing: false,
+            vi: None,
+            preview: false,
+            warned: None,
+        }
+    }
+
+    /// Replace the buffer with the given text, placing the cursor at the end
+    pub fn set_message(&mut self, text: &str) {
+        self.buffer = text.lines().map(str::to_string).collect();
+        if self.buffer.is_empty() {
+            self.buffer.push(String::new());
         }
+        self.cursor_line = self.buffer.len() - 1;
+        self.cursor_col = self.line_len();
+    }
+
+    /// Wrap lines at a fixed width instead of the terminal width
+    pub fn with_width(mut self, width: usize) -> Editor {
+        self.width = Some(width);
+        self
+    }
+
+    /// Complete @mentions, #channels and :emoji: with Tab and encode mentions on submit
+    pub fn with_completer(mut self, completer: Completer) -> Editor {
+        self.completer = Some(completer);
+        self
+    }
+
+    /// Save the buffer as a draft on every change and offer to restore
+    /// an earlier draft with the same key when the editor opens
+    pub fn with_draft(mut self, store: DraftStore, key: &str) -> Editor {
+        self.restorable = store.load(key);
+        self.saved_draft = self.restorable.clone().unwrap_or_default();
+        self.draft = Some((store, key.to_string()));
+        self
+    }
+
+    /// Recall sent messages (newest first) with Up on an empty buffer and search them with Ctrl-R
+    pub fn with_history(mut self, history: Vec<String>) -> Editor {
+        self.history = history;
+        self
+    }
+
+    /// Use modal vi keybindings instead of the default ones
+    pub fn with_vi_mode(mut self, vi_mode: bool) -> Editor {
+        self.vi = vi_mode.then(Vi::new);
+        self
     }
 
     pub fn message(&self) -> String {
@@ -44,51 +185,274 @@ impl Editor {
         stdout: &mut W,
         channel: &str,
     ) -> Result<EditorResult> {
+        write!(stdout, "{}", ENABLE_BRACKETED_PASTE)?;
+        let result = self.edit(stdin, stdout, channel);
+        write!(stdout, "{}", DISABLE_BRACKETED_PASTE)?;
+        stdout.flush()?;
+        result
+    }
+
+    /*
+     * Editor loop of `run`, with bracketed paste enabled.
+     */
+    fn edit<R: Read, W: Write>(
+        &mut self,
+        stdin: R,
+        stdout: &mut W,
+        channel: &str,
+    ) -> Result<EditorResult> {
+        let mut inputs = inputs(stdin);
         self.draw_header(stdout, channel);
 
-        for c in stdin.keys() {
-            match c? {
-                Key::Ctrl('c') => return Ok(EditorResult::Cancelled),
-                Key::Ctrl('p') => {
-                    let msg = self.message();
-                    if msg.trim().is_empty() {
-                        self.clear(stdout);
-                        continue;
-                    } else {
-                        return Ok(EditorResult::Submitted(msg));
+        if let Some(draft) = self.restorable.take() {
+            if self.is_empty() {
+                self.draw_restore_prompt(stdout, &draft);
+                match inputs.next().transpose()? {
+                    Some(Input::Key(Key::Char('y'))) | Some(Input::Key(Key::Char('\n'))) => {
+                        self.set_message(&draft)
                     }
+                    Some(Input::Key(Key::Ctrl('c'))) => return Ok(EditorResult::Cancelled),
+                    Some(Input::Paste(text)) => self.paste(&text),
+                    _ => {}
                 }
-                Key::Char('\n') => {
-                    self.new_line();
+            }
+        }
+        self.save_draft();
+        self.draw_message(stdout);
+
+        for input in inputs {
+            let indicator = self.vi.as_ref().map(Vi::indicator);
+            match input? {
+                Input::Key(key) => {
+                    if let Some(result) = self.handle_key(key, stdout) {
+                        return Ok(result);
+                    }
+                }
+                Input::Paste(text) => self.paste(&text),
+            }
+
+            self.save_draft();
+            if self.vi.as_ref().map(Vi::indicator) != indicator {
+                self.draw_header(stdout, channel);
+            }
+            self.draw_message(stdout);
+        }
+
+        // Input ended: submit as Ctrl-P would. Nothing can answer formatting
+        // warnings any more, so a message with warnings is kept as a draft.
+        Ok(self.submit(stdout).unwrap_or(EditorResult::Cancelled))
+    }
+
+    /*
+     * Submit the message with mentions encoded, unless it is blank or has
+     * formatting warnings that were not shown yet.
+     */
+    fn submit(&mut self, stdout: &mut dyn Write) -> Option<EditorResult> {
+        let msg = self.message();
+        if msg.trim().is_empty() {
+            self.clear(stdout);
+        } else if !mrkdwn::lint(&msg).is_empty() && self.warned.as_ref() != Some(&msg) {
+            // Show the warnings first; posting the same text again goes ahead
+            self.warned = Some(msg);
+        } else if let Some(completer) = &self.completer {
+            return Some(EditorResult::Submitted(completer.encode(&msg)));
+        } else {
+            return Some(EditorResult::Submitted(msg));
+        }
+        None
+    }
+
+    /// Insert pasted text at the cursor as a single edit.
+    /// Newlines start new lines; nothing in the text is handled as a key command.
+    pub fn paste(&mut self, text: &str) {
+        self.completion = None;
+        self.search = None;
+        let before = self.snapshot();
+        self.typing = false;
+        self.record(before, false);
+
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let index = self.byte_index(self.cursor_col);
+        let rest = self.buffer[self.cursor_line].split_off(index);
+
+        let mut lines = text.split('\n');
+        if let Some(first) = lines.next() {
+            self.buffer[self.cursor_line].push_str(first);
+        }
+        for line in lines {
+            self.cursor_line += 1;
+            self.buffer.insert(self.cursor_line, line.to_string());
+        }
+
+        self.cursor_col = self.line_len();
+        self.buffer[self.cursor_line].push_str(&rest);
+    }
+
+    /// Apply a key to the buffer
+    /// Returns Some(EditorResult) when the key submits or cancels the message
+    pub fn handle_key(&mut self, key: Key, stdout: &mut dyn Write) -> Option<EditorResult> {
+        let vi_command = self.in_vi_command_mode();
+        if self.search.is_none() {
+            let vi_normal = self.vi_mode() == Some(&ViMode::Normal);
+            match key {
+                Key::Ctrl('z') | Key::Ctrl('7') => {
+                    self.undo();
+                    return None;
                 }
-                Key::Char(c) => {
-                    self.insert(c);
+                Key::Ctrl('y') => {
+                    self.redo();
+                    return None;
                 }
-                Key::Up => {
-                    self.cursor_up();
+                Key::Char('u') if vi_normal => {
+                    self.undo();
+                    return None;
                 }
-                Key::Down => {
-                    self.cursor_down();
+                Key::Ctrl('r') if vi_normal => {
+                    self.redo();
+                    return None;
                 }
-                Key::Backspace => {
-                    self.backspace(stdout);
+                Key::Esc if self.vi.is_some() && !vi_command => {
+                    self.enter_normal_mode();
+                    return None;
+                }
+                // Esc followed quickly by another key arrives as Alt
+                Key::Alt(c) if self.vi.is_some() => {
+                    if !vi_command {
+                        self.enter_normal_mode();
+                    }
+                    return self.handle_key(Key::Char(c), stdout);
                 }
                 _ => {}
             }
+        }
 
-            self.draw_message(stdout);
+        let before = self.snapshot();
+        let result = if vi_command {
+            self.apply_vi_key(key, stdout)
+        } else {
+            self.apply_key(key, stdout)
+        };
+
+        // Typing a word is undone at once; anything else is an edit of its own
+        let typing = !vi_command && matches!(key, Key::Char(c) if !c.is_whitespace());
+        if self.buffer != before.buffer {
+            let continues_typing = typing && self.typing;
+            self.record(before, continues_typing);
+        }
+        self.typing = typing;
+
+        result
+    }
+
+    /*
+     * Apply a key to the buffer without recording undo history.
+     */
+    fn apply_key(&mut self, key: Key, stdout: &mut dyn Write) -> Option<EditorResult> {
+        if std::mem::take(&mut self.ctrl_x) && key == Key::Ctrl('e') {
+            return Some(EditorResult::External(self.buffer.join("\n")));
+        }
+
+        if self.search.is_some() && self.handle_search_key(key) {
+            return None;
+        }
+        if key != Key::Char('\t') {
+            self.completion = None;
+        }
+
+        match key {
+            Key::Ctrl('x') => {
+                self.ctrl_x = true;
+            }
+            Key::Ctrl('c') => return Some(EditorResult::Cancelled),
+            Key::Ctrl('p') => return self.submit(stdout),
+            Key::Ctrl('o') => {
+                self.preview = !self.preview;
+            }
+            Key::Ctrl('r') if !self.history.is_empty() => {
+                self.start_search();
+            }
+            // Tab inserts itself when there is nothing to complete
+            Key::Char('\t') if self.complete() => {}
+            Key::Char('\n') => {
+                self.new_line();
+            }
+            Key::Char(c) => {
+                self.insert(c);
+            }
+            // Up and Down recall sent messages on an empty or recalled buffer
+            Key::Up if self.recall_older() => {}
+            Key::Down if self.recall_newer() => {}
+            Key::Up => {
+                self.cursor_up();
+            }
+            Key::Down => {
+                self.cursor_down();
+            }
+            Key::Left => {
+                self.cursor_left();
+            }
+            Key::Right => {
+                self.cursor_right();
+            }
+            Key::Alt('b') => {
+                self.word_left();
+            }
+            Key::Alt('f') => {
+                self.word_right();
+            }
+            Key::Home | Key::Ctrl('a') => {
+                self.cursor_home();
+            }
+            Key::End | Key::Ctrl('e') => {
+                self.cursor_end();
+            }
+            Key::Backspace => {
+                self.backspace(stdout);
+            }
+            Key::Delete => {
+                self.delete();
+            }
+            Key::Ctrl('w') => {
+                self.delete_word_before();
+            }
+            Key::Alt('d') => {
+                self.delete_word_after();
+            }
+            Key::Ctrl('u') => {
+                self.delete_to_line_start();
+            }
+            Key::Ctrl('k') => {
+                self.delete_to_line_end();
+            }
+            _ => {}
         }
 
-        Ok(EditorResult::Submitted(self.message()))
+        None
+    }
+
+    /// Lines of the buffer
+    pub fn lines(&self) -> &[String] {
+        &self.buffer
+    }
+
+    /// Cursor position as (line, char column)
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.cursor_line, self.cursor_col)
     }
 
     pub fn draw_header(&self, stdout: &mut dyn Write, channel: &str) {
+        let mode = self
+            .vi
+            .as_ref()
+            .map(|vi| format!(" {}", vi.indicator()))
+            .unwrap_or_default();
         write!(
             stdout,
-            "{}{}#{}{}{}{}",
+            "{}{}#{}{}{}{}{}",
             termion::cursor::Goto(1, 1),
             termion::clear::All,
             &channel,
+            mode,
             termion::cursor::Goto(1, 2),
             USAGE_EDITOR,
             termion::cursor::Goto(1, 3)
@@ -97,51 +461,584 @@ impl Editor {
         stdout.flush().unwrap();
     }
 
+    /*
+     * Message length next to the usage line, in red when it is over Slack's limit.
+     */
+    fn draw_counter(&self, stdout: &mut dyn Write) {
+        let length = self.buffer.join("\n").chars().count();
+        let color: &dyn std::fmt::Display = if length > MAX_MESSAGE_LENGTH {
+            &termion::color::Fg(termion::color::Red)
+        } else {
+            &termion::style::Faint
+        };
+        write!(
+            stdout,
+            "{}{}{}  {}{}/{}{}",
+            termion::cursor::Goto(1, 2),
+            termion::clear::CurrentLine,
+            USAGE_EDITOR,
+            color,
+            length,
+            MAX_MESSAGE_LENGTH,
+            termion::style::Reset
+        )
+        .unwrap();
+    }
+
+    fn draw_restore_prompt(&self, stdout: &mut dyn Write, draft: &str) {
+        write!(
+            stdout,
+            "{}{}Restore the unsent draft? (y/n){}{}{}",
+            termion::cursor::Goto(1, MESSAGE_TOP),
+            termion::clear::AfterCursor,
+            termion::style::Faint,
+            draft
+                .lines()
+                .map(|line| format!("\r\n{}", line))
+                .collect::<String>(),
+            termion::style::Reset
+        )
+        .unwrap();
+        stdout.flush().unwrap();
+    }
+
     pub fn draw_message(&mut self, stdout: &mut dyn Write) {
+        let width = self.width.unwrap_or_else(|| term_size().0 as usize);
+        let layout = self.layout(width);
+
+        self.draw_counter(stdout);
+
+        // Clear everything below the header so shorter or removed lines leave no garbage
         write!(
             stdout,
             "{}{}{}",
-            termion::cursor::Goto(1, 3),
-            termion::clear::CurrentLine,
-            self.message()
+            termion::cursor::Goto(1, MESSAGE_TOP),
+            termion::clear::AfterCursor,
+            layout.rows.join("\r\n")
         )
         .unwrap();
+        if let Some(hint) = self.hint(width) {
+            write!(
+                stdout,
+                "\r\n{}{}{}",
+                termion::style::Faint,
+                hint,
+                termion::style::Reset
+            )
+            .unwrap();
+        }
+        if self.preview {
+            let rule = "─".repeat(width.saturating_sub(10).min(40));
+            write!(
+                stdout,
+                "\r\n{}── preview {}{}",
+                termion::style::Faint,
+                rule,
+                termion::style::Reset
+            )
+            .unwrap();
+            for line in mrkdwn::render(&self.buffer.join("\n"), self.completer.as_ref()) {
+                write!(stdout, "\r\n{}{}", line, termion::style::Reset).unwrap();
+            }
+        }
         write!(
             stdout,
             "{}",
             termion::cursor::Goto(
-                self.buffer[self.cursor_line].len() as u16 + 1,
-                self.cursor_line as u16 + 3
+                layout.cursor.1 as u16 + 1,
+                layout.cursor.0 as u16 + MESSAGE_TOP
             )
         )
         .unwrap();
         stdout.flush().unwrap();
     }
 
+    /*
+     * Write the buffer to the draft store if it changed since the last save.
+     * Drafts are best effort, so failing to save never interrupts editing.
+     */
+    fn save_draft(&mut self) {
+        let Some((store, key)) = &self.draft else {
+            return;
+        };
+        let text = self.buffer.join("\n");
+        if text != self.saved_draft {
+            store.save(key, &text).ok();
+            self.saved_draft = text;
+        }
+    }
+
+    /*
+     * Soft-wrap buffer lines by display width and locate the cursor.
+     * Wide characters are never split across rows.
+     */
+    fn layout(&self, width: usize) -> Layout {
+        let width = width.max(2);
+        let mut rows = Vec::new();
+        let mut cursor = (0, 0);
+
+        for (line_index, line) in self.buffer.iter().enumerate() {
+            let mut row = String::new();
+            let mut used = 0;
+
+            for (col, c) in line.chars().enumerate() {
+                let char_width = c.width().unwrap_or(0);
+                if used + char_width > width {
+                    rows.push(std::mem::take(&mut row));
+                    used = 0;
+                }
+                if line_index == self.cursor_line && col == self.cursor_col {
+                    cursor = (rows.len(), used);
+                }
+                row.push(c);
+                used += char_width;
+            }
+
+            if line_index == self.cursor_line && self.cursor_col >= line.chars().count() {
+                // Cursor after the last char; move to the next row when the row is full
+                if used >= width {
+                    rows.push(std::mem::take(&mut row));
+                    used = 0;
+                }
+                cursor = (rows.len(), used);
+            }
+            rows.push(row);
+        }
+
+        Layout { rows, cursor }
+    }
+
+    /// Complete the word before the cursor, or replace it with the next candidate
+    /// when called again. Returns false if there is nothing to complete.
+    pub fn complete(&mut self) -> bool {
+        if let Some(completion) = &mut self.completion {
+            completion.index = (completion.index + 1) % completion.candidates.len();
+            let (start, candidate) = (
+                completion.start,
+                completion.candidates[completion.index].clone(),
+            );
+            self.replace_word(start, &candidate);
+            return true;
+        }
+
+        let Some(completer) = &self.completer else {
+            return false;
+        };
+        let chars: Vec<char> = self.buffer[self.cursor_line].chars().collect();
+        let mut start = self.cursor_col;
+        while start > 0 && !chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        if !chars.get(start).is_some_and(|c| Completer::is_trigger(*c)) {
+            return false;
+        }
+
+        let word: String = chars[start..self.cursor_col].iter().collect();
+        let candidates = completer.candidates(&word);
+        match candidates.len() {
+            0 => return false,
+            1 => {
+                self.replace_word(start, &candidates[0]);
+                self.insert(' ');
+            }
+            _ => {
+                self.replace_word(start, &candidates[0]);
+                self.completion = Some(Completion {
+                    start,
+                    candidates,
+                    index: 0,
+                });
+            }
+        }
+        true
+    }
+
+    /*
+     * Replace the text between a char column and the cursor.
+     */
+    fn replace_word(&mut self, start: usize, text: &str) {
+        let (from, to) = (self.byte_index(start), self.byte_index(self.cursor_col));
+        self.buffer[self.cursor_line].replace_range(from..to, text);
+        self.cursor_col = start + text.chars().count();
+    }
+
+    /// Line shown below the message: the vi command line, the history search,
+    /// the completion in progress or formatting warnings, cut at the given width
+    pub fn hint(&self, width: usize) -> Option<String> {
+        let hint = self
+            .vi_hint()
+            .or_else(|| self.search_hint())
+            .or_else(|| self.completion_hint())
+            .or_else(|| self.warning_hint())?;
+        let mut used = 0;
+        Some(
+            hint.chars()
+                .take_while(|c| {
+                    used += c.width().unwrap_or(0);
+                    used <= width
+                })
+                .collect(),
+        )
+    }
+
+    /*
+     * Candidates of the completion in progress, the current one in brackets.
+     */
+    fn completion_hint(&self) -> Option<String> {
+        let completion = self.completion.as_ref()?;
+        Some(
+            completion
+                .candidates
+                .iter()
+                .enumerate()
+                .map(|(i, candidate)| {
+                    if i == completion.index {
+                        format!("[{}]", candidate)
+                    } else {
+                        candidate.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    }
+
+    /*
+     * Formatting warnings shown by Ctrl-P, until the message changes.
+     */
+    fn warning_hint(&self) -> Option<String> {
+        let warned = self.warned.as_ref().filter(|&msg| *msg == self.message())?;
+        Some(format!(
+            "Warning: {} (ctrl-p again to post anyway)",
+            mrkdwn::lint(warned).join(", ")
+        ))
+    }
+
+    fn search_hint(&self) -> Option<String> {
+        let search = self.search.as_ref()?;
+        let prefix = if search.failed { "failing " } else { "" };
+        Some(format!(
+            "({}reverse-i-search)`{}': ctrl-r older / enter accept / ctrl-g abort",
+            prefix, search.query
+        ))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buffer.len() == 1 && self.buffer[0].is_empty()
+    }
+
+    /*
+     * Check if the buffer still holds the recalled history entry unchanged.
+     */
+    fn is_recalled(&self) -> bool {
+        self.recall
+            .is_some_and(|index| self.history[index] == self.buffer.join("\n"))
+    }
+
+    /*
+     * Replace an empty or recalled buffer with the next older sent message (Up).
+     * Returns false if Up should move the cursor instead.
+     */
+    fn recall_older(&mut self) -> bool {
+        let next = if self.is_recalled() {
+            self.recall.unwrap_or_default() + 1
+        } else if self.is_empty() {
+            0
+        } else {
+            return false;
+        };
+
+        match self.history.get(next).cloned() {
+            Some(text) => {
+                self.set_message(&text);
+                self.recall = Some(next);
+                true
+            }
+            // Stay on the oldest message
+            None => next > 0,
+        }
+    }
+
+    /*
+     * Replace a recalled buffer with the next newer sent message, or clear it
+     * after the newest one (Down). Returns false if Down should move the cursor instead.
+     */
+    fn recall_newer(&mut self) -> bool {
+        if !self.is_recalled() {
+            return false;
+        }
+
+        match self.recall.unwrap_or_default().checked_sub(1) {
+            Some(index) => {
+                let text = self.history[index].clone();
+                self.set_message(&text);
+                self.recall = Some(index);
+            }
+            None => {
+                self.set_message("");
+                self.recall = None;
+            }
+        }
+        true
+    }
+
+    /*
+     * Start a reverse incremental search through sent messages (Ctrl-R).
+     */
+    fn start_search(&mut self) {
+        self.search = Some(Search {
+            query: String::new(),
+            index: None,
+            failed: false,
+            original: self.buffer.join("\n"),
+        });
+    }
+
+    /*
+     * Apply a key while searching. Returns false if the key ends the search
+     * and should be handled as a normal key.
+     */
+    fn handle_search_key(&mut self, key: Key) -> bool {
+        let Some(search) = &mut self.search else {
+            return false;
+        };
+
+        match key {
+            Key::Ctrl('r') => {
+                let from = search.index.map_or(0, |index| index + 1);
+                self.search_from(from);
+            }
+            Key::Backspace => {
+                search.query.pop();
+                self.search_from(0);
+            }
+            Key::Ctrl('g') | Key::Ctrl('c') => {
+                let original = search.original.clone();
+                self.set_message(&original);
+                self.search = None;
+            }
+            Key::Char('\n') | Key::Esc => {
+                self.search = None;
+            }
+            Key::Char(c) => {
+                search.query.push(c);
+                let from = search.index.unwrap_or_default();
+                self.search_from(from);
+            }
+            _ => {
+                self.search = None;
+                return false;
+            }
+        }
+        true
+    }
+
+    /*
+     * Show the first sent message from `from` on containing the query, ignoring case.
+     */
+    fn search_from(&mut self, from: usize) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        let query = search.query.to_lowercase();
+        let found = self
+            .history
+            .iter()
+            .enumerate()
+            .skip(from)
+            .find(|(_, text)| text.to_lowercase().contains(&query));
+
+        match found {
+            Some((index, text)) => {
+                search.index = Some(index);
+                search.failed = false;
+                let text = text.clone();
+                self.set_message(&text);
+                self.recall = Some(index);
+            }
+            None => search.failed = true,
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            buffer: self.buffer.clone(),
+            cursor: self.cursor(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.buffer = snapshot.buffer;
+        (self.cursor_line, self.cursor_col) = snapshot.cursor;
+        self.completion = None;
+        self.typing = false;
+    }
+
+    /*
+     * Remember the buffer before an edit. Edits extending the previous one
+     * keep its snapshot, and any new edit makes undone edits unavailable.
+     */
+    fn record(&mut self, before: Snapshot, extends_previous: bool) {
+        if !extends_previous || self.undo.is_empty() {
+            self.undo.push(before);
+            if self.undo.len() > UNDO_LIMIT {
+                self.undo.remove(0);
+            }
+        }
+        self.redo.clear();
+    }
+
+    /// Revert the last edit (Ctrl-Z / Ctrl-_)
+    pub fn undo(&mut self) {
+        if let Some(snapshot) = self.undo.pop() {
+            self.redo.push(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    /// Apply the last undone edit again (Ctrl-Y)
+    pub fn redo(&mut self) {
+        if let Some(snapshot) = self.redo.pop() {
+            self.undo.push(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    /*
+     * Byte offset of a char column in the current line.
+     */
+    fn byte_index(&self, col: usize) -> usize {
+        let line = &self.buffer[self.cursor_line];
+        line.char_indices()
+            .nth(col)
+            .map(|(index, _)| index)
+            .unwrap_or(line.len())
+    }
+
+    fn line_len(&self) -> usize {
+        self.buffer[self.cursor_line].chars().count()
+    }
+
     pub fn insert(&mut self, c: char) {
-        self.buffer[self.cursor_line].push(c);
+        let index = self.byte_index(self.cursor_col);
+        self.buffer[self.cursor_line].insert(index, c);
+        self.cursor_col += 1;
     }
 
-    pub fn backspace(&mut self, stdout: &mut dyn Write) {
-        if !self.buffer[self.cursor_line].is_empty() {
-            self.buffer[self.cursor_line].pop();
-            write!(
-                stdout,
-                "{}{}",
-                termion::cursor::Left(1),
-                termion::clear::AfterCursor
-            )
-            .unwrap();
-        } else if self.buffer.len() > 1 {
-            // Remove current line
-            self.buffer.remove(self.cursor_line);
+    /// Delete the char before the cursor, joining the line to the previous one
+    /// at the start of a line. The change shows on the next `draw_message`.
+    pub fn backspace(&mut self, _stdout: &mut dyn Write) {
+        if self.cursor_col > 0 {
+            let index = self.byte_index(self.cursor_col - 1);
+            self.buffer[self.cursor_line].remove(index);
+            self.cursor_col -= 1;
+        } else if self.cursor_line > 0 {
+            // Join current line to the previous line
+            let line = self.buffer.remove(self.cursor_line);
             self.cursor_line -= 1;
+            self.cursor_col = self.line_len();
+            self.buffer[self.cursor_line].push_str(&line);
+        }
+    }
+
+    /// Delete the char under the cursor, joining the next line at the end of a line
+    pub fn delete(&mut self) {
+        if self.cursor_col < self.line_len() {
+            let index = self.byte_index(self.cursor_col);
+            self.buffer[self.cursor_line].remove(index);
+        } else if self.cursor_line + 1 < self.buffer.len() {
+            let next = self.buffer.remove(self.cursor_line + 1);
+            self.buffer[self.cursor_line].push_str(&next);
         }
     }
 
+    /// Delete the whitespace-delimited word before the cursor (Ctrl-W)
+    pub fn delete_word_before(&mut self) {
+        let chars: Vec<char> = self.buffer[self.cursor_line].chars().collect();
+        let mut start = self.cursor_col;
+        while start > 0 && chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+
+        let (from, to) = (self.byte_index(start), self.byte_index(self.cursor_col));
+        self.buffer[self.cursor_line].replace_range(from..to, "");
+        self.cursor_col = start;
+    }
+
+    /*
+     * Start of the word before a char column: skip separators, then word chars.
+     */
+    fn word_start_before(&self, col: usize) -> usize {
+        let chars: Vec<char> = self.buffer[self.cursor_line].chars().collect();
+        let mut start = col;
+        while start > 0 && !is_word_char(chars[start - 1]) {
+            start -= 1;
+        }
+        while start > 0 && is_word_char(chars[start - 1]) {
+            start -= 1;
+        }
+        start
+    }
+
+    /*
+     * End of the word after a char column: skip separators, then word chars.
+     */
+    fn word_end_after(&self, col: usize) -> usize {
+        let chars: Vec<char> = self.buffer[self.cursor_line].chars().collect();
+        let mut end = col;
+        while end < chars.len() && !is_word_char(chars[end]) {
+            end += 1;
+        }
+        while end < chars.len() && is_word_char(chars[end]) {
+            end += 1;
+        }
+        end
+    }
+
+    /// Move to the start of the previous word, continuing on the previous line (Alt-B)
+    pub fn word_left(&mut self) {
+        if self.cursor_col == 0 {
+            self.cursor_left();
+        }
+        self.cursor_col = self.word_start_before(self.cursor_col);
+    }
+
+    /// Move to the end of the next word, continuing on the next line (Alt-F)
+    pub fn word_right(&mut self) {
+        if self.cursor_col == self.line_len() {
+            self.cursor_right();
+        }
+        self.cursor_col = self.word_end_after(self.cursor_col);
+    }
+
+    /// Delete from the cursor to the end of the next word (Alt-D)
+    pub fn delete_word_after(&mut self) {
+        let end = self.word_end_after(self.cursor_col);
+        let (from, to) = (self.byte_index(self.cursor_col), self.byte_index(end));
+        self.buffer[self.cursor_line].replace_range(from..to, "");
+    }
+
+    /// Delete from the start of the line to the cursor (Ctrl-U)
+    pub fn delete_to_line_start(&mut self) {
+        let to = self.byte_index(self.cursor_col);
+        self.buffer[self.cursor_line].replace_range(..to, "");
+        self.cursor_col = 0;
+    }
+
+    /// Delete from the cursor to the end of the line (Ctrl-K)
+    pub fn delete_to_line_end(&mut self) {
+        let from = self.byte_index(self.cursor_col);
+        self.buffer[self.cursor_line].truncate(from);
+    }
+
     pub fn clear(&mut self, stdout: &mut dyn Write) {
+        self.completion = None;
         self.buffer = vec![String::new()];
         self.cursor_line = 0;
+        self.cursor_col = 0;
 
         write!(
             stdout,
@@ -156,21 +1053,60 @@ impl Editor {
     pub fn cursor_up(&mut self) {
         if self.cursor_line > 0 {
             self.cursor_line -= 1;
+            self.cursor_col = self.cursor_col.min(self.line_len());
         }
     }
 
     pub fn cursor_down(&mut self) {
         if self.cursor_line < self.buffer.len() - 1 {
             self.cursor_line += 1;
+            self.cursor_col = self.cursor_col.min(self.line_len());
+        }
+    }
+
+    pub fn cursor_left(&mut self) {
+        if self.cursor_col > 0 {
+            self.cursor_col -= 1;
+        } else if self.cursor_line > 0 {
+            self.cursor_line -= 1;
+            self.cursor_col = self.line_len();
+        }
+    }
+
+    pub fn cursor_right(&mut self) {
+        if self.cursor_col < self.line_len() {
+            self.cursor_col += 1;
+        } else if self.cursor_line + 1 < self.buffer.len() {
+            self.cursor_line += 1;
+            self.cursor_col = 0;
         }
     }
 
+    pub fn cursor_home(&mut self) {
+        self.cursor_col = 0;
+    }
+
+    pub fn cursor_end(&mut self) {
+        self.cursor_col = self.line_len();
+    }
+
+    /// Split the current line at the cursor
     pub fn new_line(&mut self) {
-        self.buffer.push(String::new());
+        let index = self.byte_index(self.cursor_col);
+        let rest = self.buffer[self.cursor_line].split_off(index);
+        self.buffer.insert(self.cursor_line + 1, rest);
         self.cursor_line += 1;
+        self.cursor_col = 0;
     }
 }
 
+/*
+ * Chars that make up words for Alt-B, Alt-F and Alt-D.
+ */
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
 impl Default for Editor {
     fn default() -> Self {
         Self::new()
@@ -180,6 +1116,7 @@ impl Default for Editor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::console::screen::Screen;
 
     #[test]
     fn editor_new_creates_empty_buffer() {
@@ -333,4 +1270,684 @@ mod tests {
         assert_eq!(editor.buffer.len(), 1);
         assert_eq!(editor.cursor_line, 0);
     }
+
+    #[test]
+    fn editor_insert_in_middle_of_line() {
+        let mut editor = Editor::new();
+        editor.insert('a');
+        editor.insert('c');
+        editor.cursor_left();
+        editor.insert('b');
+        assert_eq!(editor.message(), "abc");
+        assert_eq!(editor.cursor(), (0, 2));
+    }
+
+    #[test]
+    fn editor_home_and_end() {
+        let mut editor = Editor::new();
+        for c in "ello".chars() {
+            editor.insert(c);
+        }
+        editor.cursor_home();
+        editor.insert('h');
+        editor.cursor_end();
+        editor.insert('!');
+        assert_eq!(editor.message(), "hello!");
+    }
+
+    #[test]
+    fn editor_backspace_in_middle_of_unicode_line() {
+        let mut editor = Editor::new();
+        for c in "こんにちは".chars() {
+            editor.insert(c);
+        }
+        editor.cursor_left();
+        editor.cursor_left();
+        editor.backspace(&mut Vec::new());
+        assert_eq!(editor.message(), "こんちは");
+        assert_eq!(editor.cursor(), (0, 2));
+    }
+
+    #[test]
+    fn editor_backspace_at_line_start_joins_lines() {
+        let mut editor = Editor::new();
+        editor.insert('a');
+        editor.new_line();
+        editor.insert('b');
+        editor.cursor_home();
+        editor.backspace(&mut Vec::new());
+        assert_eq!(editor.message(), "ab");
+        assert_eq!(editor.cursor(), (0, 1));
+    }
+
+    #[test]
+    fn editor_new_line_splits_line() {
+        let mut editor = Editor::new();
+        for c in "abcd".chars() {
+            editor.insert(c);
+        }
+        editor.cursor_left();
+        editor.cursor_left();
+        editor.new_line();
+        assert_eq!(editor.message(), "ab\r\ncd");
+        assert_eq!(editor.cursor(), (1, 0));
+    }
+
+    #[test]
+    fn editor_delete_joins_next_line() {
+        let mut editor = Editor::new();
+        editor.insert('a');
+        editor.insert('b');
+        editor.new_line();
+        editor.insert('c');
+        editor.cursor_up();
+        editor.delete();
+        assert_eq!(editor.message(), "a\r\nc");
+        editor.delete();
+        assert_eq!(editor.message(), "ac");
+    }
+
+    #[test]
+    fn editor_cursor_left_and_right_wrap_lines() {
+        let mut editor = Editor::new();
+        editor.insert('a');
+        editor.new_line();
+        editor.cursor_left();
+        assert_eq!(editor.cursor(), (0, 1));
+        editor.cursor_right();
+        assert_eq!(editor.cursor(), (1, 0));
+    }
+
+    #[test]
+    fn editor_readline_deletions() {
+        let mut editor = Editor::new();
+        for c in "deploy api  now".chars() {
+            editor.insert(c);
+        }
+        editor.delete_word_before();
+        assert_eq!(editor.message(), "deploy api  ");
+        editor.delete_word_before();
+        assert_eq!(editor.message(), "deploy ");
+        editor.cursor_home();
+        editor.cursor_right();
+        editor.delete_to_line_end();
+        assert_eq!(editor.message(), "d");
+        editor.insert('x');
+        editor.delete_to_line_start();
+        assert_eq!(editor.message(), "");
+        assert_eq!(editor.cursor(), (0, 0));
+    }
+
+    #[test]
+    fn editor_run_with_readline_keys() {
+        let mut editor = Editor::new();
+        let mut stdout = Vec::new();
+        // "world", Ctrl-A, "hello ", End, "!", Ctrl-P
+        let input = b"world\x01hello \x05!\x10";
+        match editor.run(&input[..], &mut stdout, "general").unwrap() {
+            EditorResult::Submitted(msg) => assert_eq!(msg, "hello world!"),
+            _ => panic!("expected submitted message"),
+        }
+    }
+
+    #[test]
+    fn layout_uses_display_width() {
+        let mut editor = Editor::new();
+        for c in "日本語".chars() {
+            editor.insert(c);
+        }
+        let layout = editor.layout(80);
+        assert_eq!(layout.rows, vec!["日本語".to_string()]);
+        assert_eq!(layout.cursor, (0, 6));
+    }
+
+    #[test]
+    fn layout_soft_wraps_long_lines() {
+        let mut editor = Editor::new();
+        for c in "abcdefg".chars() {
+            editor.insert(c);
+        }
+        editor.new_line();
+        editor.insert('h');
+        let layout = editor.layout(3);
+        assert_eq!(layout.rows, vec!["abc", "def", "g", "h"]);
+        assert_eq!(layout.cursor, (3, 1));
+    }
+
+    #[test]
+    fn layout_does_not_split_wide_chars() {
+        let mut editor = Editor::new();
+        for c in "aあい".chars() {
+            editor.insert(c);
+        }
+        editor.cursor_left();
+        let layout = editor.layout(4);
+        assert_eq!(layout.rows, vec!["aあ", "い"]);
+        assert_eq!(layout.cursor, (1, 0));
+    }
+
+    #[test]
+    fn layout_moves_cursor_after_full_row() {
+        let mut editor = Editor::new();
+        for c in "abc".chars() {
+            editor.insert(c);
+        }
+        let layout = editor.layout(3);
+        assert_eq!(layout.rows, vec!["abc", ""]);
+        assert_eq!(layout.cursor, (1, 0));
+    }
+
+    #[test]
+    fn draw_message_clears_below_header() {
+        let mut editor = Editor::new().with_width(80);
+        let mut stdout = Vec::new();
+        editor.insert('a');
+        editor.new_line();
+        editor.insert('b');
+        editor.draw_message(&mut stdout);
+        let screen = String::from_utf8(stdout).unwrap();
+        // The length counter on the usage line comes first
+        assert!(screen.ends_with(&format!(
+            "{}{}a\r\nb{}",
+            termion::cursor::Goto(1, 3),
+            termion::clear::AfterCursor,
+            termion::cursor::Goto(2, 4)
+        )));
+    }
+
+    #[test]
+    fn editor_set_message_moves_cursor_to_end() {
+        let mut editor = Editor::new();
+        editor.set_message("first\nsecond");
+        assert_eq!(editor.message(), "first\r\nsecond");
+        assert_eq!(editor.cursor(), (1, 6));
+
+        editor.set_message("");
+        assert_eq!(editor.message(), "");
+        assert_eq!(editor.cursor(), (0, 0));
+    }
+
+    #[test]
+    fn editor_run_requests_external_editor() {
+        let mut editor = Editor::new();
+        let mut stdout = Vec::new();
+        // "a", Enter, "b", Ctrl-X, Ctrl-E
+        match editor
+            .run(&b"a\nb\x18\x05"[..], &mut stdout, "general")
+            .unwrap()
+        {
+            EditorResult::External(text) => assert_eq!(text, "a\nb"),
+            _ => panic!("expected external editor request"),
+        }
+    }
+
+    #[test]
+    fn editor_ctrl_x_followed_by_other_key() {
+        let mut editor = Editor::new();
+        let mut stdout = Vec::new();
+        // Ctrl-X, "a", Ctrl-E (end of line), "b", Ctrl-P
+        match editor
+            .run(&b"\x18a\x05b\x10"[..], &mut stdout, "general")
+            .unwrap()
+        {
+            EditorResult::Submitted(msg) => assert_eq!(msg, "ab"),
+            _ => panic!("expected submitted message"),
+        }
+    }
+
+    fn completer() -> Completer {
+        use crate::slack::{SlackChannel, SlackChannels, SlackUser, SlackUsers};
+
+        let user = |id: &str, name: &str| SlackUser {
+            id: id.to_string(),
+            name: name.to_string(),
+            real_name: None,
+            profile: None,
+        };
+        let users = SlackUsers::from(vec![user("U001", "alice"), user("U002", "albert")]);
+        let channels = SlackChannels::from(vec![SlackChannel {
+            id: "C001".to_string(),
+            name: "general".to_string(),
+        }]);
+        Completer::new(&users, &channels, Vec::new())
+    }
+
+    #[test]
+    fn editor_tab_completes_and_encodes_mentions() {
+        let mut editor = Editor::new().with_completer(completer());
+        let mut stdout = Vec::new();
+        // "hi @ali", Tab, "see #gen", Tab, Ctrl-P
+        match editor
+            .run(&b"hi @ali\tsee #gen\t\x10"[..], &mut stdout, "general")
+            .unwrap()
+        {
+            EditorResult::Submitted(msg) => assert_eq!(msg, "hi <@U001> see <#C001> "),
+            _ => panic!("expected submitted message"),
+        }
+    }
+
+    #[test]
+    fn editor_end_of_input_submits_like_ctrl_p() {
+        let mut editor = Editor::new().with_completer(completer());
+        let mut stdout = Vec::new();
+        match editor
+            .run(&b"hi @alice"[..], &mut stdout, "general")
+            .unwrap()
+        {
+            EditorResult::Submitted(msg) => assert_eq!(msg, "hi <@U001>"),
+            _ => panic!("expected submitted message"),
+        }
+
+        // Warnings cannot be confirmed once input has ended
+        let mut editor = Editor::new();
+        match editor
+            .run(&b"run `make"[..], &mut stdout, "general")
+            .unwrap()
+        {
+            EditorResult::Cancelled => {}
+            _ => panic!("expected cancelled editor"),
+        }
+    }
+
+    #[test]
+    fn editor_tab_cycles_candidates() {
+        let mut editor = Editor::new().with_completer(completer()).with_width(40);
+        let mut stdout = Vec::new();
+        for key in [
+            Key::Char('@'),
+            Key::Char('a'),
+            Key::Char('l'),
+            Key::Char('\t'),
+        ] {
+            editor.handle_key(key, &mut stdout);
+        }
+        assert_eq!(editor.lines()[0], "@albert");
+        assert_eq!(editor.hint(40).unwrap(), "[@albert] @alice");
+
+        editor.handle_key(Key::Char('\t'), &mut stdout);
+        assert_eq!(editor.lines()[0], "@alice");
+        assert_eq!(editor.cursor(), (0, 6));
+
+        editor.handle_key(Key::Char('!'), &mut stdout);
+        assert_eq!(editor.lines()[0], "@alice!");
+        assert!(editor.hint(40).is_none());
+    }
+
+    #[test]
+    fn editor_tab_without_candidates_inserts_tab() {
+        let mut editor = Editor::new().with_completer(completer());
+        let mut stdout = Vec::new();
+        for key in [
+            Key::Char('a'),
+            Key::Char('\t'),
+            Key::Char('@'),
+            Key::Char('z'),
+            Key::Char('\t'),
+        ] {
+            editor.handle_key(key, &mut stdout);
+        }
+        assert_eq!(editor.lines()[0], "a\t@z\t");
+    }
+
+    fn draft_store(name: &str) -> DraftStore {
+        let dir = std::env::temp_dir().join(format!(
+            "rslack-editor-drafts-{}-{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::remove_dir_all(&dir).ok();
+        DraftStore::new(dir)
+    }
+
+    #[test]
+    fn editor_saves_draft_while_typing() {
+        let store = draft_store("save");
+        let mut editor = Editor::new()
+            .with_width(40)
+            .with_draft(store.clone(), "C001");
+        let mut stdout = Vec::new();
+        match editor
+            .run(&b"hi\nthere\x03"[..], &mut stdout, "general")
+            .unwrap()
+        {
+            EditorResult::Cancelled => {}
+            _ => panic!("expected cancelled editor"),
+        }
+        assert_eq!(store.load("C001"), Some("hi\nthere".to_string()));
+    }
+
+    #[test]
+    fn editor_restores_draft_on_confirmation() {
+        let store = draft_store("restore");
+        store.save("C001", "draft").unwrap();
+        let mut editor = Editor::new()
+            .with_width(40)
+            .with_draft(store.clone(), "C001");
+        let mut stdout = Vec::new();
+        match editor.run(&b"y!\x10"[..], &mut stdout, "general").unwrap() {
+            EditorResult::Submitted(msg) => assert_eq!(msg, "draft!"),
+            _ => panic!("expected submitted message"),
+        }
+        // The draft is kept until the message is posted
+        assert_eq!(store.load("C001"), Some("draft!".to_string()));
+    }
+
+    #[test]
+    fn editor_discards_declined_draft() {
+        let store = draft_store("decline");
+        store.save("C001", "draft").unwrap();
+        let mut editor = Editor::new()
+            .with_width(40)
+            .with_draft(store.clone(), "C001");
+        let mut stdout = Vec::new();
+        match editor
+            .run(&b"nnew\x10"[..], &mut stdout, "general")
+            .unwrap()
+        {
+            EditorResult::Submitted(msg) => assert_eq!(msg, "new"),
+            _ => panic!("expected submitted message"),
+        }
+        assert_eq!(store.load("C001"), Some("new".to_string()));
+    }
+
+    fn history() -> Vec<String> {
+        vec![
+            "deploying web to staging".to_string(),
+            "lunch?".to_string(),
+            "deploying api to staging".to_string(),
+        ]
+    }
+
+    #[test]
+    fn editor_up_and_down_recall_sent_messages() {
+        let mut editor = Editor::new().with_history(history());
+        let mut stdout = Vec::new();
+        editor.handle_key(Key::Up, &mut stdout);
+        assert_eq!(editor.message(), "deploying web to staging");
+        editor.handle_key(Key::Up, &mut stdout);
+        assert_eq!(editor.message(), "lunch?");
+        editor.handle_key(Key::Down, &mut stdout);
+        assert_eq!(editor.message(), "deploying web to staging");
+        editor.handle_key(Key::Down, &mut stdout);
+        assert_eq!(editor.message(), "");
+    }
+
+    #[test]
+    fn editor_up_moves_cursor_once_recalled_message_is_edited() {
+        let mut editor = Editor::new().with_history(history());
+        let mut stdout = Vec::new();
+        editor.set_message("line 1\nline 2");
+        editor.handle_key(Key::Up, &mut stdout);
+        assert_eq!(editor.message(), "line 1\r\nline 2");
+        assert_eq!(editor.cursor(), (0, 6));
+    }
+
+    #[test]
+    fn editor_run_searches_sent_messages() {
+        let mut editor = Editor::new().with_width(80).with_history(history());
+        let mut stdout = Vec::new();
+        // Ctrl-R "deploy", Ctrl-R (older match), Enter, edit, Ctrl-P
+        match editor
+            .run(&b"\x12deploy\x12\n!\x10"[..], &mut stdout, "general")
+            .unwrap()
+        {
+            EditorResult::Submitted(msg) => assert_eq!(msg, "deploying api to staging!"),
+            _ => panic!("expected submitted message"),
+        }
+    }
+
+    #[test]
+    fn editor_search_abort_restores_buffer() {
+        let mut editor = Editor::new().with_width(80).with_history(history());
+        let mut stdout = Vec::new();
+        for key in [
+            Key::Char('x'),
+            Key::Ctrl('r'),
+            Key::Char('l'),
+            Key::Char('u'),
+        ] {
+            editor.handle_key(key, &mut stdout);
+        }
+        assert_eq!(editor.message(), "lunch?");
+        assert!(editor
+            .hint(80)
+            .unwrap()
+            .starts_with("(reverse-i-search)`lu'"));
+
+        editor.handle_key(Key::Char('z'), &mut stdout);
+        assert!(editor
+            .hint(80)
+            .unwrap()
+            .starts_with("(failing reverse-i-search)"));
+
+        assert!(editor.handle_key(Key::Ctrl('g'), &mut stdout).is_none());
+        assert_eq!(editor.message(), "x");
+        assert!(editor.hint(80).is_none());
+    }
+
+    #[test]
+    fn editor_paste_inserts_lines_at_cursor() {
+        let mut editor = Editor::new();
+        editor.set_message("before after");
+        editor.cursor_col = 7;
+        editor.paste("one\r\ntwo\tthree\nfour ");
+        assert_eq!(editor.lines(), ["before one", "two\tthree", "four after"]);
+        assert_eq!(editor.cursor(), (2, 5));
+    }
+
+    #[test]
+    fn editor_run_never_handles_pasted_text_as_keys() {
+        let mut editor = Editor::new().with_width(80);
+        let mut stdout = Vec::new();
+        // A paste containing a newline, Ctrl-P and Ctrl-C, then Ctrl-P to post
+        match editor
+            .run(
+                &b"\x1b[200~at main.rs:1\n\x10\x03at lib.rs:2\x1b[201~\x10"[..],
+                &mut stdout,
+                "general",
+            )
+            .unwrap()
+        {
+            EditorResult::Submitted(msg) => assert_eq!(msg, "at main.rs:1\r\nat lib.rs:2"),
+            _ => panic!("expected submitted message"),
+        }
+
+        let output = String::from_utf8(stdout).unwrap();
+        assert!(output.starts_with(ENABLE_BRACKETED_PASTE));
+        assert!(output.ends_with(DISABLE_BRACKETED_PASTE));
+    }
+
+    #[test]
+    fn editor_run_undoes_and_redoes_edits() {
+        let mut editor = Editor::new().with_width(80);
+        let mut stdout = Vec::new();
+        // "hello world", Ctrl-U, Ctrl-Z (undo Ctrl-U), Ctrl-Z (undo "world"),
+        // Ctrl-Y (redo "world"), "!", Ctrl-P
+        match editor
+            .run(
+                &b"hello world\x15\x1a\x1a\x19!\x10"[..],
+                &mut stdout,
+                "general",
+            )
+            .unwrap()
+        {
+            EditorResult::Submitted(msg) => assert_eq!(msg, "hello world!"),
+            _ => panic!("expected submitted message"),
+        }
+    }
+
+    #[test]
+    fn editor_undo_restores_backspace_run_and_cursor() {
+        let mut editor = Editor::new();
+        let mut stdout = Vec::new();
+        for c in "abc".chars() {
+            editor.handle_key(Key::Char(c), &mut stdout);
+        }
+        editor.handle_key(Key::Left, &mut stdout);
+        editor.handle_key(Key::Backspace, &mut stdout);
+        editor.handle_key(Key::Backspace, &mut stdout);
+        assert_eq!(editor.message(), "c");
+
+        editor.handle_key(Key::Ctrl('7'), &mut stdout);
+        assert_eq!(editor.message(), "ac");
+        editor.handle_key(Key::Ctrl('7'), &mut stdout);
+        assert_eq!(editor.message(), "abc");
+        assert_eq!(editor.cursor(), (0, 2));
+        editor.handle_key(Key::Ctrl('7'), &mut stdout);
+        assert_eq!(editor.message(), "");
+        editor.handle_key(Key::Ctrl('7'), &mut stdout);
+        assert_eq!(editor.message(), "");
+    }
+
+    #[test]
+    fn editor_new_edit_discards_redo() {
+        let mut editor = Editor::new();
+        let mut stdout = Vec::new();
+        for key in [
+            Key::Char('a'),
+            Key::Ctrl('z'),
+            Key::Char('b'),
+            Key::Ctrl('y'),
+        ] {
+            editor.handle_key(key, &mut stdout);
+        }
+        assert_eq!(editor.message(), "b");
+    }
+
+    #[test]
+    fn editor_undo_reverts_paste_at_once() {
+        let mut editor = Editor::new();
+        let mut stdout = Vec::new();
+        editor.handle_key(Key::Char('a'), &mut stdout);
+        editor.paste("b\nc");
+        editor.handle_key(Key::Ctrl('z'), &mut stdout);
+        assert_eq!(editor.message(), "a");
+    }
+
+    #[test]
+    fn editor_run_moves_and_deletes_by_word() {
+        let mut editor = Editor::new().with_width(80);
+        let mut stdout = Vec::new();
+        // "deploy api-v2 now", Alt-B twice (to "v2"), Alt-B (to "api"), Alt-D ("api"),
+        // Alt-F (after "v2"), "3", Ctrl-P
+        match editor
+            .run(
+                &b"deploy api-v2 now\x1bb\x1bb\x1bb\x1bd\x1bf3\x10"[..],
+                &mut stdout,
+                "general",
+            )
+            .unwrap()
+        {
+            EditorResult::Submitted(msg) => assert_eq!(msg, "deploy -v23 now"),
+            _ => panic!("expected submitted message"),
+        }
+    }
+
+    #[test]
+    fn editor_word_motion_crosses_lines() {
+        let mut editor = Editor::new();
+        editor.set_message("one\ntwo");
+        editor.cursor_col = 0;
+        editor.word_left();
+        assert_eq!(editor.cursor(), (0, 0));
+        editor.word_right();
+        assert_eq!(editor.cursor(), (0, 3));
+        editor.word_right();
+        assert_eq!(editor.cursor(), (1, 3));
+    }
+
+    #[test]
+    fn editor_warns_about_formatting_before_posting() {
+        let mut editor = Editor::new().with_width(80);
+        let mut stdout = Vec::new();
+        for c in "run `make".chars() {
+            editor.handle_key(Key::Char(c), &mut stdout);
+        }
+        assert!(editor.handle_key(Key::Ctrl('p'), &mut stdout).is_none());
+        assert_eq!(
+            editor.hint(80).unwrap(),
+            "Warning: Unclosed inline code (`) on line 1 (ctrl-p again to post anyway)"
+        );
+
+        match editor.handle_key(Key::Ctrl('p'), &mut stdout) {
+            Some(EditorResult::Submitted(msg)) => assert_eq!(msg, "run `make"),
+            _ => panic!("expected submitted message"),
+        }
+    }
+
+    #[test]
+    fn editor_fixing_warnings_clears_them() {
+        let mut editor = Editor::new().with_width(80);
+        let mut stdout = Vec::new();
+        match editor
+            .run(&b"run `make\x10`\x10"[..], &mut stdout, "general")
+            .unwrap()
+        {
+            EditorResult::Submitted(msg) => assert_eq!(msg, "run `make`"),
+            _ => panic!("expected submitted message"),
+        }
+        assert!(editor.hint(80).is_none());
+    }
+
+    #[test]
+    fn editor_toggles_preview() {
+        let mut editor = Editor::new().with_width(40);
+        editor.set_message("*bold*");
+        let mut stdout = Vec::new();
+        editor.handle_key(Key::Ctrl('o'), &mut stdout);
+        editor.draw_message(&mut stdout);
+        let output = String::from_utf8(stdout).unwrap();
+        assert!(output.contains("── preview"));
+        assert!(output.contains(&format!(
+            "{}bold{}",
+            termion::style::Bold,
+            termion::style::NoBold
+        )));
+
+        let mut stdout = Vec::new();
+        editor.handle_key(Key::Ctrl('o'), &mut stdout);
+        editor.draw_message(&mut stdout);
+        assert!(!String::from_utf8(stdout).unwrap().contains("── preview"));
+    }
+
+    #[test]
+    fn draw_message_shows_length_counter() {
+        let mut editor = Editor::new().with_width(40);
+        editor.set_message("hello\nworld");
+        let mut stdout = Vec::new();
+        editor.draw_message(&mut stdout);
+        let output = String::from_utf8(stdout).unwrap();
+        assert!(output.contains(&format!("{}11/40000", termion::style::Faint)));
+
+        editor.set_message(&"a".repeat(MAX_MESSAGE_LENGTH + 1));
+        let mut stdout = Vec::new();
+        editor.draw_message(&mut stdout);
+        let output = String::from_utf8(stdout).unwrap();
+        assert!(output.contains(&format!(
+            "{}40001/40000",
+            termion::color::Fg(termion::color::Red)
+        )));
+    }
+
+    #[test]
+    fn run_renders_wrapped_message_and_preview() {
+        let mut editor = Editor::new().with_width(20);
+        let mut screen = Screen::new(120, 16);
+        // Ctrl-O turns on the preview
+        let input = "Deploying *api* to\n日本語 staging after the review\x0f";
+        match editor
+            .run(input.as_bytes(), &mut screen, "deploys")
+            .unwrap()
+        {
+            EditorResult::Submitted(_) => {}
+            _ => panic!("expected the message at the end of input"),
+        }
+        screen.assert_snapshot("editor");
+
+        assert!(!screen.bracketed_paste());
+        assert_eq!(screen.cursor(), (12, 5));
+        let (x, y) = screen.find("Deploying api to").unwrap();
+        assert!(screen.style_at(x + 10, y).bold);
+        assert!(!screen.style_at(x, y).bold);
+    }
 }
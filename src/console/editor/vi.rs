@@ -0,0 +1,334 @@
+use std::io::Write;
+
+use termion::event::Key;
+
+use super::{is_word_char, Editor, EditorResult};
+
+/// Modes of the vi keybindings
+#[derive(Clone, Debug, PartialEq)]
+pub(super) enum ViMode {
+    Insert,
+    Normal,
+    /// Typing an ex command after `:`
+    Command(String),
+}
+
+/// State of the vi keybindings
+pub(super) struct Vi {
+    pub(super) mode: ViMode,
+    /// First key of a two-key command such as `dd`
+    pending: Option<char>,
+    /// Error from the last ex command
+    pub(super) error: Option<String>,
+}
+
+impl Vi {
+    /// Start in insert mode like vi modes of shells, so typing works right away
+    pub(super) fn new() -> Self {
+        Self {
+            mode: ViMode::Insert,
+            pending: None,
+            error: None,
+        }
+    }
+
+    /// Label shown in the header
+    pub(super) fn indicator(&self) -> &'static str {
+        match self.mode {
+            ViMode::Insert => "-- INSERT --",
+            ViMode::Normal | ViMode::Command(_) => "-- NORMAL --",
+        }
+    }
+}
+
+impl Editor {
+    pub(super) fn vi_mode(&self) -> Option<&ViMode> {
+        self.vi.as_ref().map(|vi| &vi.mode)
+    }
+
+    /*
+     * Check if keys are vi commands rather than text.
+     */
+    pub(super) fn in_vi_command_mode(&self) -> bool {
+        self.vi_mode().is_some_and(|mode| *mode != ViMode::Insert)
+    }
+
+    fn set_vi_mode(&mut self, mode: ViMode) {
+        if let Some(vi) = &mut self.vi {
+            vi.mode = mode;
+            vi.pending = None;
+        }
+    }
+
+    /*
+     * Leave insert mode. Like vi, the cursor moves onto the last typed char.
+     */
+    pub(super) fn enter_normal_mode(&mut self) {
+        self.set_vi_mode(ViMode::Normal);
+        self.cursor_col = self.cursor_col.saturating_sub(1);
+        self.completion = None;
+    }
+
+    /*
+     * Keep the cursor on a char in normal mode.
+     */
+    fn clamp_to_line(&mut self) {
+        self.cursor_col = self.cursor_col.min(self.line_len().saturating_sub(1));
+    }
+
+    /*
+     * Start of the next word (`w`), continuing on the next line.
+     */
+    fn vi_word_forward(&mut self) {
+        let chars: Vec<char> = self.buffer[self.cursor_line].chars().collect();
+        let mut col = self.cursor_col;
+        while col < chars.len() && is_word_char(chars[col]) {
+            col += 1;
+        }
+        while col < chars.len() && !is_word_char(chars[col]) {
+            col += 1;
+        }
+
+        if col >= chars.len() && self.cursor_line + 1 < self.buffer.len() {
+            self.cursor_line += 1;
+            self.cursor_col = 0;
+        } else {
+            self.cursor_col = col;
+            self.clamp_to_line();
+        }
+    }
+
+    /*
+     * Delete the current line (`dd`). The last line is emptied instead.
+     */
+    fn vi_delete_line(&mut self) {
+        if self.buffer.len() == 1 {
+            self.buffer[0].clear();
+        } else {
+            self.buffer.remove(self.cursor_line);
+            self.cursor_line = self.cursor_line.min(self.buffer.len() - 1);
+        }
+        self.cursor_col = 0;
+    }
+
+    /*
+     * Open an empty line below (`o`) or above (`O`) the cursor in insert mode.
+     */
+    fn vi_open_line(&mut self, below: bool) {
+        if below {
+            self.cursor_line += 1;
+        }
+        self.buffer.insert(self.cursor_line, String::new());
+        self.cursor_col = 0;
+        self.set_vi_mode(ViMode::Insert);
+    }
+
+    /// Apply a key in normal or command mode
+    pub(super) fn apply_vi_key(
+        &mut self,
+        key: Key,
+        stdout: &mut dyn Write,
+    ) -> Option<EditorResult> {
+        let vi = self.vi.as_mut()?;
+        vi.error = None;
+
+        if let ViMode::Command(command) = &mut vi.mode {
+            match key {
+                Key::Char('\n') => {
+                    let command = command.clone();
+                    self.set_vi_mode(ViMode::Normal);
+                    return self.run_vi_command(&command, stdout);
+                }
+                Key::Esc => self.set_vi_mode(ViMode::Normal),
+                Key::Backspace if command.is_empty() => self.set_vi_mode(ViMode::Normal),
+                Key::Backspace => {
+                    command.pop();
+                }
+                Key::Char(c) => command.push(c),
+                Key::Ctrl('c') => return Some(EditorResult::Cancelled),
+                _ => {}
+            }
+            return None;
+        }
+
+        if let Some(first) = vi.pending.take() {
+            if (first, key) == ('d', Key::Char('d')) {
+                self.vi_delete_line();
+            }
+            return None;
+        }
+
+        match key {
+            Key::Char('i') => self.set_vi_mode(ViMode::Insert),
+            Key::Char('a') => {
+                self.cursor_col = (self.cursor_col + 1).min(self.line_len());
+                self.set_vi_mode(ViMode::Insert);
+            }
+            Key::Char('I') => {
+                self.cursor_home();
+                self.set_vi_mode(ViMode::Insert);
+            }
+            Key::Char('A') => {
+                self.cursor_end();
+                self.set_vi_mode(ViMode::Insert);
+            }
+            Key::Char('o') => self.vi_open_line(true),
+            Key::Char('O') => self.vi_open_line(false),
+            Key::Char(':') => self.set_vi_mode(ViMode::Command(String::new())),
+            Key::Char('h') | Key::Left | Key::Backspace => {
+                self.cursor_col = self.cursor_col.saturating_sub(1);
+            }
+            Key::Char('l') | Key::Right | Key::Char(' ') => {
+                self.cursor_col += 1;
+                self.clamp_to_line();
+            }
+            Key::Char('k') | Key::Up => {
+                self.cursor_up();
+                self.clamp_to_line();
+            }
+            Key::Char('j') | Key::Down => {
+                self.cursor_down();
+                self.clamp_to_line();
+            }
+            Key::Char('0') | Key::Home => self.cursor_home(),
+            Key::Char('$') | Key::End => {
+                self.cursor_end();
+                self.clamp_to_line();
+            }
+            Key::Char('w') => self.vi_word_forward(),
+            Key::Char('b') => self.word_left(),
+            Key::Char('x') | Key::Delete if self.cursor_col < self.line_len() => {
+                self.delete();
+                self.clamp_to_line();
+            }
+            Key::Char('d') => {
+                if let Some(vi) = &mut self.vi {
+                    vi.pending = Some('d');
+                }
+            }
+            // Keys shared with insert mode: post, cancel, $EDITOR and history search
+            Key::Ctrl(_) => return self.apply_key(key, stdout),
+            _ => {}
+        }
+
+        None
+    }
+
+    /*
+     * Run an ex command typed after `:`.
+     */
+    fn run_vi_command(&mut self, command: &str, stdout: &mut dyn Write) -> Option<EditorResult> {
+        match command.trim() {
+            "w" | "wq" | "x" => self.apply_key(Key::Ctrl('p'), stdout),
+            "q" | "q!" => Some(EditorResult::Cancelled),
+            "" => None,
+            other => {
+                if let Some(vi) = &mut self.vi {
+                    vi.error = Some(format!("Not an editor command: {}", other));
+                }
+                None
+            }
+        }
+    }
+
+    /*
+     * Command line or error shown below the message.
+     */
+    pub(super) fn vi_hint(&self) -> Option<String> {
+        let vi = self.vi.as_ref()?;
+        match &vi.mode {
+            ViMode::Command(command) => Some(format!(":{}", command)),
+            _ => vi.error.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(input: &[u8]) -> (Editor, EditorResult) {
+        let mut editor = Editor::new().with_width(80).with_vi_mode(true);
+        let mut stdout = Vec::new();
+        let result = editor.run(input, &mut stdout, "general").unwrap();
+        (editor, result)
+    }
+
+    fn submitted(input: &[u8]) -> String {
+        match run(input).1 {
+            EditorResult::Submitted(msg) => msg,
+            _ => panic!("expected submitted message"),
+        }
+    }
+
+    #[test]
+    fn vi_starts_in_insert_mode_and_posts_with_w() {
+        // "hello", Esc, ":w", Enter. A key typed right after Esc arrives as Alt.
+        assert_eq!(submitted(b"hello\x1b:w\n"), "hello");
+    }
+
+    #[test]
+    fn vi_quit_cancels() {
+        match run(b"hello\x1b:q\n").1 {
+            EditorResult::Cancelled => {}
+            _ => panic!("expected cancelled editor"),
+        }
+    }
+
+    #[test]
+    fn vi_motions_and_deletions() {
+        // "one two three", Esc, "0", "w" (to "two"), "x", "$", "x", "b", "i", "[", Esc, ":w"
+        assert_eq!(
+            submitted(b"one two three\x1b0wx$xbi[\x1b:w\n"),
+            "one wo [thre"
+        );
+    }
+
+    #[test]
+    fn vi_dd_and_open_lines() {
+        // Three lines, Esc, "k" (to line 2), "dd", "o" + "below", Esc, "h", "O" + "above",
+        // Esc, ":w". Esc directly followed by "O" would read as a function key.
+        assert_eq!(
+            submitted(b"1\n2\n3\x1bkddobelow\x1bhOabove\x1b:w\n"),
+            "1\r\n3\r\nabove\r\nbelow"
+        );
+    }
+
+    #[test]
+    fn vi_unknown_command_shows_error() {
+        let mut editor = Editor::new().with_vi_mode(true);
+        let mut stdout = Vec::new();
+        for key in [Key::Esc, Key::Char(':'), Key::Char('e')] {
+            editor.handle_key(key, &mut stdout);
+        }
+        assert_eq!(editor.vi_hint(), Some(":e".to_string()));
+        editor.handle_key(Key::Char('\n'), &mut stdout);
+        assert_eq!(
+            editor.vi_hint(),
+            Some("Not an editor command: e".to_string())
+        );
+        assert_eq!(editor.vi_mode(), Some(&ViMode::Normal));
+    }
+
+    #[test]
+    fn vi_u_undoes_in_normal_mode() {
+        assert_eq!(submitted(b"one two\x1bbddu:w\n"), "one two");
+    }
+
+    #[test]
+    fn header_shows_mode() {
+        let mut editor = Editor::new().with_vi_mode(true);
+        let mut stdout = Vec::new();
+        editor.draw_header(&mut stdout, "general");
+        assert!(String::from_utf8(stdout)
+            .unwrap()
+            .contains("#general -- INSERT --"));
+
+        editor.handle_key(Key::Esc, &mut Vec::new());
+        let mut stdout = Vec::new();
+        editor.draw_header(&mut stdout, "general");
+        assert!(String::from_utf8(stdout)
+            .unwrap()
+            .contains("#general -- NORMAL --"));
+    }
+}
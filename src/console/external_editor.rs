@@ -0,0 +1,166 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Stdout, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use termion::raw::RawTerminal;
+use termion::screen::{AlternateScreen, ToAlternateScreen, ToMainScreen};
+
+const DEFAULT_EDITOR: &str = "vi";
+/// Names tried before giving up on creating a temporary file
+const TEMP_FILE_ATTEMPTS: u32 = 100;
+
+/// Terminal in raw mode showing the alternate screen
+pub type Screen = AlternateScreen<RawTerminal<Stdout>>;
+
+/*
+ * Editor command from $VISUAL or $EDITOR, split into program and arguments.
+ */
+fn editor_command() -> Vec<String> {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string())
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+/*
+ * Create a temporary file that only the current user can read and write.
+ * Existing paths are never opened, so a file or symlink planted in a shared
+ * temporary directory cannot be written through or read.
+ */
+fn create_temp_file() -> Result<(PathBuf, File)> {
+    let dir = env::temp_dir();
+    for attempt in 0..TEMP_FILE_ATTEMPTS {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.subsec_nanos())
+            .unwrap_or_default();
+        let path = dir.join(format!(
+            "rslack-{}-{}-{}.txt",
+            std::process::id(),
+            nanos,
+            attempt
+        ));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+        {
+            Ok(file) => return Ok((path, file)),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(anyhow!("{}: {}", path.display(), err)),
+        }
+    }
+    anyhow::bail!("Failed to create a temporary file in {}", dir.display())
+}
+
+/// Edit text in $VISUAL / $EDITOR using a temporary file seeded with `initial`
+pub fn edit(initial: &str) -> Result<String> {
+    let (path, mut file) = create_temp_file()?;
+    let written = file.write_all(initial.as_bytes());
+    drop(file);
+    if let Err(err) = written {
+        fs::remove_file(&path).ok();
+        return Err(err.into());
+    }
+
+    let command = editor_command();
+    let status = Command::new(&command[0])
+        .args(&command[1..])
+        .arg(&path)
+        .status()
+        .map_err(|err| anyhow!("Failed to start {}: {}", command[0], err));
+
+    let edited = fs::read_to_string(&path);
+    fs::remove_file(&path).ok();
+
+    if !status?.success() {
+        anyhow::bail!("{} exited with an error", command[0]);
+    }
+
+    Ok(edited?.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Leave raw mode and the alternate screen while the external editor runs,
+/// and restore them afterwards
+pub fn edit_in_terminal(screen: &mut Screen, initial: &str) -> Result<String> {
+    write!(screen, "{}{}", ToMainScreen, termion::cursor::Show)?;
+    screen.flush()?;
+    screen.suspend_raw_mode()?;
+
+    let edited = edit(initial);
+
+    screen.activate_raw_mode()?;
+    write!(screen, "{}", ToAlternateScreen)?;
+    screen.flush()?;
+
+    edited
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    fn setup(visual: Option<&str>, editor: Option<&str>) {
+        match visual {
+            Some(visual) => env::set_var("VISUAL", visual),
+            None => env::remove_var("VISUAL"),
+        }
+        match editor {
+            Some(editor) => env::set_var("EDITOR", editor),
+            None => env::remove_var("EDITOR"),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn editor_command_prefers_visual() {
+        setup(Some("code --wait"), Some("vim"));
+        assert_eq!(editor_command(), vec!["code", "--wait"]);
+
+        setup(None, Some("vim"));
+        assert_eq!(editor_command(), vec!["vim"]);
+
+        setup(None, None);
+        assert_eq!(editor_command(), vec![DEFAULT_EDITOR]);
+    }
+
+    #[test]
+    #[serial]
+    fn edit_returns_edited_text() {
+        setup(None, Some("sed -i s/draft/final/"));
+        let edited = edit("draft announcement\n").unwrap();
+        assert_eq!(edited, "final announcement");
+    }
+
+    #[test]
+    fn temp_files_are_private_and_new() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (first, _) = create_temp_file().unwrap();
+        let (second, _) = create_temp_file().unwrap();
+        assert_ne!(first, second);
+        let mode = fs::metadata(&first).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        fs::remove_file(first).unwrap();
+        fs::remove_file(second).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn edit_fails_when_editor_fails() {
+        setup(None, Some("false"));
+        assert!(edit("draft").is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use std::io::{self, Read};
+
+use termion::event::{Event, Key};
+use termion::input::{Events, TermRead};
+
+/// Ask the terminal to wrap pasted text in `ESC [200~` ... `ESC [201~`
+pub const ENABLE_BRACKETED_PASTE: &str = "\x1b[?2004h";
+pub const DISABLE_BRACKETED_PASTE: &str = "\x1b[?2004l";
+
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// Keyboard input with pasted text kept apart from typed keys
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    Key(Key),
+    /// Text pasted in bracketed paste mode, never to be handled as key commands
+    Paste(String),
+}
+
+/// Iterator over keys and pasted text read from the terminal
+pub struct Inputs<R> {
+    events: Events<R>,
+}
+
+impl<R: Read> Iterator for Inputs<R> {
+    type Item = io::Result<Input>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.events.next()? {
+                Ok(Event::Key(key)) => return Some(Ok(Input::Key(key))),
+                Ok(Event::Unsupported(bytes)) if bytes == PASTE_START => {
+                    return Some(self.read_paste().map(Input::Paste))
+                }
+                Ok(_) => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+impl<R: Read> Inputs<R> {
+    /*
+     * Collect pasted text up to the end marker. Newlines and tabs are kept,
+     * other control keys are dropped so they can never act as commands.
+     */
+    fn read_paste(&mut self) -> io::Result<String> {
+        let mut text = String::new();
+        for event in self.events.by_ref() {
+            match event? {
+                Event::Unsupported(bytes) if bytes == PASTE_END => break,
+                Event::Key(Key::Char(c)) => text.push(c),
+                _ => {}
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// Read keys and bracketed pastes from the terminal
+pub fn inputs<R: Read>(stdin: R) -> Inputs<R> {
+    Inputs {
+        events: stdin.events(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inputs_separate_pasted_text_from_keys() {
+        let stdin = &b"a\x1b[200~line 1\nline 2\tend\x10\x1b[201~\x10"[..];
+        let inputs: Vec<Input> = inputs(stdin).map(Result::unwrap).collect();
+        assert_eq!(
+            inputs,
+            vec![
+                Input::Key(Key::Char('a')),
+                Input::Paste("line 1\nline 2\tend".to_string()),
+                Input::Key(Key::Ctrl('p')),
+            ]
+        );
+    }
+
+    #[test]
+    fn inputs_end_unterminated_paste_at_end_of_input() {
+        let stdin = &b"\x1b[200~hello"[..];
+        let inputs: Vec<Input> = inputs(stdin).map(Result::unwrap).collect();
+        assert_eq!(inputs, vec![Input::Paste("hello".to_string())]);
+    }
+}
@@ -3,7 +3,7 @@ use std::io::Write;
 use chrono::{Local, TimeZone};
 use termion::{clear, color, cursor, style};
 
-use crate::slack::SlackMessage;
+use crate::slack::{SlackMessage, SlackSearchMatch};
 
 /// Displays messages from a Slack channel
 pub struct MessageViewer {
@@ -39,13 +39,26 @@ impl MessageViewer {
 
         // Messages (reversed to show oldest first)
         let mut line = 3u16;
+        for text_line in self.lines(messages) {
+            write!(stdout, "{}{}", cursor::Goto(1, line), text_line).unwrap();
+            line += 1;
+        }
+
+        write!(stdout, "{}", cursor::Goto(1, line)).unwrap();
+        write!(stdout, "{}", "─".repeat(60)).unwrap();
+
+        stdout.flush().unwrap();
+    }
+
+    /// Format messages as terminal lines, oldest first, with a blank line after each message
+    pub fn lines(&self, messages: &[SlackMessage]) -> Vec<String> {
+        let mut lines = Vec::new();
+
         for message in messages.iter().rev() {
             let timestamp = self.format_timestamp(&message.ts);
             let user = message.user.as_deref().unwrap_or("unknown");
 
-            write!(stdout, "{}", cursor::Goto(1, line)).unwrap();
-            write!(
-                stdout,
+            lines.push(format!(
                 "{}{}[{}] {}{}{}",
                 color::Fg(color::Green),
                 style::Bold,
@@ -53,25 +66,17 @@ impl MessageViewer {
                 user,
                 style::Reset,
                 color::Fg(color::Reset)
-            )
-            .unwrap();
-
-            line += 1;
+            ));
 
             // Handle multi-line messages
             for text_line in message.text.lines() {
-                write!(stdout, "{}", cursor::Goto(1, line)).unwrap();
-                write!(stdout, "  {}", text_line).unwrap();
-                line += 1;
+                lines.push(format!("  {}", text_line));
             }
 
-            line += 1; // Add spacing between messages
+            lines.push(String::new()); // Add spacing between messages
         }
 
-        write!(stdout, "{}", cursor::Goto(1, line)).unwrap();
-        write!(stdout, "{}", "─".repeat(60)).unwrap();
-
-        stdout.flush().unwrap();
+        lines
     }
 
     /// Format Slack timestamp (e.g., "1234567890.123456") to readable format
@@ -93,18 +98,38 @@ pub fn print_messages(channel_name: &str, messages: &[SlackMessage]) {
     println!("{}", "─".repeat(60));
 
     for message in messages.iter().rev() {
-        let timestamp = format_timestamp_simple(&message.ts);
         let user = message.user.as_deref().unwrap_or("unknown");
+        print_message(&format_timestamp_simple(&message.ts), user, &message.text);
+        println!();
+    }
+}
 
-        println!("[{}] {}", timestamp, user);
-        for line in message.text.lines() {
-            println!("  {}", line);
-        }
+/// Print search results to stdout (non-interactive, simple format)
+pub fn print_search_results(query: &str, matches: &[SlackSearchMatch]) {
+    println!("\"{}\" - {} results", query, matches.len());
+    println!("{}", "─".repeat(60));
+
+    for found in matches {
+        let user = found
+            .username
+            .as_deref()
+            .or(found.user.as_deref())
+            .unwrap_or("unknown");
+        let header = format!("#{} {}", found.channel.name, user);
+        print_message(&format_timestamp_simple(&found.ts), &header, &found.text);
+        println!("  {}", found.permalink);
         println!();
     }
 }
 
-fn format_timestamp_simple(ts: &str) -> String {
+fn print_message(timestamp: &str, user: &str, text: &str) {
+    println!("[{}] {}", timestamp, user);
+    for line in text.lines() {
+        println!("  {}", line);
+    }
+}
+
+pub(crate) fn format_timestamp_simple(ts: &str) -> String {
     let epoch_secs: f64 = ts.parse().unwrap_or(0.0);
     let secs = epoch_secs as i64;
 
@@ -117,6 +142,8 @@ fn format_timestamp_simple(ts: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::console::screen::{Color, Screen};
+    use serial_test::serial;
 
     #[test]
     fn message_viewer_new() {
@@ -132,4 +159,63 @@ mod tests {
         // Result depends on local timezone, just check it's not the original
         assert!(!result.is_empty());
     }
+
+    #[test]
+    fn lines_are_oldest_first() {
+        let viewer = MessageViewer::new("test");
+        let messages = vec![
+            SlackMessage {
+                text: "second".to_string(),
+                user: Some("U2".to_string()),
+                ts: "1609459260.000000".to_string(),
+                thread_ts: None,
+                reply_count: None,
+            },
+            SlackMessage {
+                text: "first\nline two".to_string(),
+                user: None,
+                ts: "1609459200.000000".to_string(),
+                thread_ts: None,
+                reply_count: None,
+            },
+        ];
+        let lines = viewer.lines(&messages);
+        assert_eq!(lines.len(), 7);
+        assert!(lines[0].contains("unknown"));
+        assert_eq!(lines[1], "  first");
+        assert_eq!(lines[2], "  line two");
+        assert_eq!(lines[5], "  second");
+    }
+
+    #[test]
+    #[serial]
+    fn display_renders_header_and_messages() {
+        std::env::set_var("TZ", "UTC");
+        let viewer = MessageViewer::new("general");
+        let messages = vec![
+            SlackMessage {
+                text: "Deploying api v1.2.0 to staging".to_string(),
+                user: Some("U0000000001".to_string()),
+                ts: "1609459260.000000".to_string(),
+                thread_ts: None,
+                reply_count: None,
+            },
+            SlackMessage {
+                text: "Good morning\n日本語も大丈夫".to_string(),
+                user: None,
+                ts: "1609459200.000000".to_string(),
+                thread_ts: None,
+                reply_count: None,
+            },
+        ];
+        let mut screen = Screen::new(64, 12);
+        viewer.display(&mut screen, &messages);
+        std::env::remove_var("TZ");
+        screen.assert_snapshot("message_viewer");
+
+        assert!(screen.style_at(1, 1).bold);
+        assert_eq!(screen.style_at(1, 1).fg, Some(Color::Indexed(6)));
+        assert_eq!(screen.style_at(1, 3).fg, Some(Color::Indexed(2)));
+        assert_eq!(screen.style_at(1, 4), Default::default());
+    }
 }
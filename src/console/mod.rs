@@ -1,9 +1,20 @@
+mod app;
 mod channel_selector;
+mod completion;
 mod editor;
+pub mod external_editor;
+mod input;
 mod message_viewer;
+mod mrkdwn;
+pub mod prompt;
+#[cfg(test)]
+mod screen;
 mod table;
 
+pub use app::{Action, App, Focus, History};
 pub use channel_selector::{ChannelSelector, SelectionResult};
+pub use completion::Completer;
 pub use editor::{Editor, EditorResult};
-pub use message_viewer::{print_messages, MessageViewer};
+pub(crate) use message_viewer::format_timestamp_simple;
+pub use message_viewer::{print_messages, print_search_results, MessageViewer};
 pub use table::Table;
@@ -0,0 +1,307 @@
+use termion::{color, style};
+
+use super::Completer;
+
+const FENCE: &str = "```";
+
+/// Render a message the way Slack formats mrkdwn: *bold*, _italic_, ~strike~,
+/// `code`, code blocks, > quotes, mentions and <url|links>
+pub fn render(text: &str, completer: Option<&Completer>) -> Vec<String> {
+    let text = match completer {
+        Some(completer) => completer.encode(text),
+        None => text.to_string(),
+    };
+
+    let mut lines = Vec::new();
+    let mut in_code_block = false;
+    for line in text.lines() {
+        let fences = line.matches(FENCE).count();
+        if in_code_block || fences > 0 {
+            lines.push(format!(
+                "{}{}{}",
+                color::Fg(color::LightRed),
+                line.replace(FENCE, ""),
+                color::Fg(color::Reset)
+            ));
+            if fences % 2 == 1 {
+                in_code_block = !in_code_block;
+            }
+        } else if let Some(quote) = line.strip_prefix('>') {
+            lines.push(format!(
+                "{}▌{} {}",
+                style::Faint,
+                style::NoFaint,
+                render_inline(quote.trim_start(), completer)
+            ));
+        } else {
+            lines.push(render_inline(line, completer));
+        }
+    }
+    lines
+}
+
+/// Common formatting mistakes in a message, as human readable warnings
+pub fn lint(text: &str) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if text.matches(FENCE).count() % 2 == 1 {
+        warnings.push("Unclosed code block (```)".to_string());
+    }
+
+    let mut in_code_block = false;
+    for (index, line) in text.lines().enumerate() {
+        let fences = line.matches(FENCE).count();
+        if in_code_block || fences > 0 {
+            if fences % 2 == 1 {
+                in_code_block = !in_code_block;
+            }
+            continue;
+        }
+
+        let line_number = index + 1;
+        if line.matches('`').count() % 2 == 1 {
+            warnings.push(format!("Unclosed inline code (`) on line {}", line_number));
+        }
+        if has_markdown_link(line) {
+            warnings.push(format!(
+                "Markdown link on line {}, use <url|text>",
+                line_number
+            ));
+        }
+        if line.contains("**") {
+            warnings.push(format!("**bold** on line {}, use *bold*", line_number));
+        }
+        if line.starts_with("# ") || line.starts_with("## ") {
+            warnings.push(format!(
+                "Markdown heading on line {} is not formatted",
+                line_number
+            ));
+        }
+    }
+    warnings
+}
+
+/*
+ * Check for `[text](http...)`.
+ */
+fn has_markdown_link(line: &str) -> bool {
+    line.match_indices("](").any(|(index, _)| {
+        line[..index].contains('[')
+            && line[index + 2..].starts_with("http")
+            && line[index + 2..].contains(')')
+    })
+}
+
+/*
+ * Render inline formatting of a line outside code blocks.
+ */
+fn render_inline(line: &str, completer: Option<&Completer>) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut rendered = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '`' => {
+                if let Some(end) = chars[i + 1..].iter().position(|&c| c == '`') {
+                    let code: String = chars[i + 1..i + 1 + end].iter().collect();
+                    rendered.push_str(&format!(
+                        "{}{}{}",
+                        color::Fg(color::LightRed),
+                        code,
+                        color::Fg(color::Reset)
+                    ));
+                    i += end + 2;
+                    continue;
+                }
+            }
+            '*' | '_' | '~' => {
+                if let Some(end) = closing_marker(&chars, i) {
+                    let inner: String = chars[i + 1..end].iter().collect();
+                    let inner = render_inline(&inner, completer);
+                    rendered.push_str(&match c {
+                        '*' => format!("{}{}{}", style::Bold, inner, style::NoBold),
+                        '_' => format!("{}{}{}", style::Italic, inner, style::NoItalic),
+                        _ => format!("{}{}{}", style::CrossedOut, inner, style::NoCrossedOut),
+                    });
+                    i = end + 1;
+                    continue;
+                }
+            }
+            '<' => {
+                if let Some(end) = chars[i + 1..].iter().position(|&c| c == '>') {
+                    let target: String = chars[i + 1..i + 1 + end].iter().collect();
+                    rendered.push_str(&render_link(&target, completer));
+                    i += end + 2;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+
+        rendered.push(c);
+        i += 1;
+    }
+
+    rendered
+}
+
+/*
+ * Position of the marker closing the one at `start`. Like Slack, markers must
+ * sit at word boundaries and enclose text that does not start or end with a space.
+ */
+fn closing_marker(chars: &[char], start: usize) -> Option<usize> {
+    let marker = chars[start];
+    if start > 0 && chars[start - 1].is_alphanumeric() {
+        return None;
+    }
+    if chars.get(start + 1).is_none_or(|c| c.is_whitespace()) {
+        return None;
+    }
+
+    (start + 2..chars.len()).find(|&end| {
+        chars[end] == marker
+            && !chars[end - 1].is_whitespace()
+            && chars.get(end + 1).is_none_or(|c| !c.is_alphanumeric())
+    })
+}
+
+/*
+ * Render `<@U…>`, `<#C…>`, `<!here>` and `<url|label>`.
+ */
+fn render_link(target: &str, completer: Option<&Completer>) -> String {
+    let (target, label) = match target.split_once('|') {
+        Some((target, label)) => (target, Some(label)),
+        None => (target, None),
+    };
+
+    let mention = if let Some(id) = target.strip_prefix('@') {
+        let name = label.or_else(|| completer.and_then(|c| c.user_name(id)));
+        Some(format!("@{}", name.unwrap_or(id)))
+    } else if let Some(id) = target.strip_prefix('#') {
+        let name = label.or_else(|| completer.and_then(|c| c.channel_name(id)));
+        Some(format!("#{}", name.unwrap_or(id)))
+    } else {
+        target
+            .strip_prefix('!')
+            .map(|special| format!("@{}", special))
+    };
+
+    match mention {
+        Some(mention) => format!(
+            "{}{}{}",
+            color::Fg(color::LightBlue),
+            mention,
+            color::Fg(color::Reset)
+        ),
+        None => format!(
+            "{}{}{}",
+            style::Underline,
+            label.unwrap_or(target),
+            style::NoUnderline
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slack::{SlackChannel, SlackChannels, SlackUser, SlackUsers};
+
+    fn completer() -> Completer {
+        let users = SlackUsers::from(vec![SlackUser {
+            id: "U001".to_string(),
+            name: "alice".to_string(),
+            real_name: None,
+            profile: None,
+        }]);
+        let channels = SlackChannels::from(vec![SlackChannel {
+            id: "C001".to_string(),
+            name: "general".to_string(),
+        }]);
+        Completer::new(&users, &channels, Vec::new())
+    }
+
+    #[test]
+    fn render_inline_formatting() {
+        assert_eq!(
+            render("*bold* _it_ ~no~ `x*y*`", None),
+            vec![format!(
+                "{}bold{} {}it{} {}no{} {}x*y*{}",
+                style::Bold,
+                style::NoBold,
+                style::Italic,
+                style::NoItalic,
+                style::CrossedOut,
+                style::NoCrossedOut,
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset)
+            )]
+        );
+    }
+
+    #[test]
+    fn render_leaves_markers_inside_words() {
+        assert_eq!(
+            render("snake_case_name 2*3*4", None),
+            vec!["snake_case_name 2*3*4"]
+        );
+    }
+
+    #[test]
+    fn render_code_blocks_and_quotes() {
+        let lines = render("```\nlet *a* = 1;\n```\n> quoted", None);
+        assert_eq!(
+            lines[1],
+            format!(
+                "{}let *a* = 1;{}",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset)
+            )
+        );
+        assert_eq!(
+            lines[3],
+            format!("{}▌{} quoted", style::Faint, style::NoFaint)
+        );
+    }
+
+    #[test]
+    fn render_mentions_and_links() {
+        let blue = |s: &str| {
+            format!(
+                "{}{}{}",
+                color::Fg(color::LightBlue),
+                s,
+                color::Fg(color::Reset)
+            )
+        };
+        assert_eq!(
+            render(
+                "@alice #general @here <https://example.com|docs>",
+                Some(&completer())
+            ),
+            vec![format!(
+                "{} {} {} {}docs{}",
+                blue("@alice"),
+                blue("#general"),
+                blue("@here"),
+                style::Underline,
+                style::NoUnderline
+            )]
+        );
+    }
+
+    #[test]
+    fn lint_common_mistakes() {
+        assert_eq!(
+            lint("see `main\n[docs](https://example.com)\n**important**\n```\ncode"),
+            vec![
+                "Unclosed code block (```)",
+                "Unclosed inline code (`) on line 1",
+                "Markdown link on line 2, use <url|text>",
+                "**bold** on line 3, use *bold*",
+            ]
+        );
+        assert!(lint("*bold* <https://example.com|docs>\n```\n`a\n```").is_empty());
+    }
+}
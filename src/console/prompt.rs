@@ -0,0 +1,121 @@
+use std::io::{BufRead, Read, Write};
+
+use anyhow::Result;
+use termion::event::Key;
+
+use super::input::{inputs, Input};
+use crate::config::Config;
+use crate::slack::{broadcast_mentions, split_message, SlackChannel, MAX_MESSAGE_LENGTH};
+
+/// Ask a yes/no question on a cleared screen, listing the reasons for asking.
+/// Only `y` confirms; pasted text never does.
+pub fn confirm<R: Read, W: Write>(
+    stdin: R,
+    stdout: &mut W,
+    question: &str,
+    reasons: &[String],
+) -> Result<bool> {
+    write!(
+        stdout,
+        "{}{}",
+        termion::cursor::Goto(1, 1),
+        termion::clear::All
+    )?;
+    for reason in reasons {
+        write!(stdout, "{}\r\n", reason)?;
+    }
+    write!(stdout, "\r\n{} (y/n)", question)?;
+    stdout.flush()?;
+
+    for input in inputs(stdin) {
+        match input? {
+            Input::Key(Key::Char('y')) | Input::Key(Key::Char('Y')) => return Ok(true),
+            Input::Key(_) => return Ok(false),
+            Input::Paste(_) => {}
+        }
+    }
+
+    Ok(false)
+}
+
+/// Reasons to confirm posting a message: protected channels, `@channel`-style
+/// broadcasts when configured, and text over Slack's length limit.
+/// An empty list means the message can be posted without asking.
+pub fn post_warnings(config: &Config, channels: &[SlackChannel], message: &str) -> Vec<String> {
+    let mut reasons: Vec<String> = channels
+        .iter()
+        .filter(|channel| config.is_protected(&channel.name))
+        .map(|channel| format!("#{} is a protected channel.", channel.name))
+        .collect();
+
+    let broadcasts = broadcast_mentions(message);
+    if config.confirm_broadcasts() && !broadcasts.is_empty() {
+        reasons.push(format!(
+            "The message notifies everyone with @{}.",
+            broadcasts.join(", @")
+        ));
+    }
+
+    let length = message.chars().count();
+    if length > MAX_MESSAGE_LENGTH {
+        reasons.push(format!(
+            "The message has {} characters, over Slack's limit of {}. It will be posted as {} messages.",
+            length,
+            MAX_MESSAGE_LENGTH,
+            split_message(message, MAX_MESSAGE_LENGTH).len()
+        ));
+    }
+
+    reasons
+}
+
+/// Ask for a line of text on a terminal in normal (cooked) mode.
+/// Fails at the end of input, e.g. when stdin is not a terminal.
+pub fn ask<R: BufRead, W: Write>(mut stdin: R, stdout: &mut W, label: &str) -> Result<String> {
+    write!(stdout, "{}: ", label)?;
+    stdout.flush()?;
+
+    let mut line = String::new();
+    if stdin.read_line(&mut line)? == 0 {
+        anyhow::bail!("No value given for {}", label);
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confirm_with_y() {
+        let mut stdout = Vec::new();
+        let reasons = vec!["#general is protected".to_string()];
+        assert!(confirm(&b"y"[..], &mut stdout, "Post?", &reasons).unwrap());
+
+        let output = String::from_utf8(stdout).unwrap();
+        assert!(output.contains("#general is protected\r\n"));
+        assert!(output.ends_with("Post? (y/n)"));
+    }
+
+    #[test]
+    fn other_keys_and_end_of_input_decline() {
+        let mut stdout = Vec::new();
+        assert!(!confirm(&b"n"[..], &mut stdout, "Post?", &[]).unwrap());
+        assert!(!confirm(&b"\n"[..], &mut stdout, "Post?", &[]).unwrap());
+        assert!(!confirm(&b"\x1b[200~y\x1b[201~"[..], &mut stdout, "Post?", &[]).unwrap());
+    }
+
+    #[test]
+    fn ask_reads_a_line() {
+        let mut stdout = Vec::new();
+        let answer = ask(&b"v1.2.0\nrest\n"[..], &mut stdout, "version").unwrap();
+        assert_eq!(answer, "v1.2.0");
+        assert_eq!(String::from_utf8(stdout).unwrap(), "version: ");
+    }
+
+    #[test]
+    fn ask_fails_at_end_of_input() {
+        let mut stdout = Vec::new();
+        assert!(ask(&b""[..], &mut stdout, "version").is_err());
+    }
+}
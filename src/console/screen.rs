@@ -0,0 +1,510 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use unicode_width::UnicodeWidthChar;
+
+const SNAPSHOT_DIR: &str = "tests/fixtures/screens";
+/// Set to rewrite snapshot files instead of comparing against them
+const UPDATE_SNAPSHOTS: &str = "UPDATE_SNAPSHOTS";
+
+/// Terminal color of a cell
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Color {
+    /// One of the 256 palette colors, e.g. 7 for `color::White`
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+/// Graphic rendition of a cell
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub faint: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub inverse: bool,
+    pub crossed_out: bool,
+}
+
+#[derive(Clone, Default)]
+struct Cell {
+    /// Char shown in the cell, empty for a blank cell or the right half of a wide char
+    symbol: String,
+    style: Style,
+    /// Right half of a wide char
+    continuation: bool,
+}
+
+enum State {
+    Ground,
+    Escape,
+    /// Control sequence with the parameter bytes read so far
+    Csi(String),
+}
+
+/// Virtual terminal for testing the console widgets without a tty.
+/// It interprets the escape sequences termion writes (cursor movement, clearing,
+/// colors and styles, cursor visibility, alternate screen and bracketed paste)
+/// so tests can assert on what a user would see.
+pub struct Screen {
+    width: usize,
+    height: usize,
+    cells: Vec<Vec<Cell>>,
+    /// Cursor as (column, row), 0-based. The column equals `width`
+    /// after writing the last column, until the next char wraps.
+    cursor: (usize, usize),
+    saved_cursor: (usize, usize),
+    style: Style,
+    cursor_visible: bool,
+    bracketed_paste: bool,
+    /// Main screen while the alternate screen is shown
+    main: Option<Vec<Vec<Cell>>>,
+    state: State,
+    /// Bytes of an incomplete UTF-8 char
+    pending: Vec<u8>,
+}
+
+impl Screen {
+    pub fn new(width: u16, height: u16) -> Self {
+        let (width, height) = (width as usize, height as usize);
+        Self {
+            width,
+            height,
+            cells: vec![vec![Cell::default(); width]; height],
+            cursor: (0, 0),
+            saved_cursor: (0, 0),
+            style: Style::default(),
+            cursor_visible: true,
+            bracketed_paste: false,
+            main: None,
+            state: State::Ground,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Text of every row, without trailing spaces
+    pub fn rows(&self) -> Vec<String> {
+        self.cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| match cell.symbol.as_str() {
+                        "" if !cell.continuation => " ",
+                        symbol => symbol,
+                    })
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    /// Text of a row, 1-based like `cursor::Goto`
+    pub fn row(&self, y: u16) -> String {
+        self.rows().swap_remove(y as usize - 1)
+    }
+
+    /// Rows joined by newlines, without the blank rows at the bottom
+    pub fn text(&self) -> String {
+        let mut rows = self.rows();
+        while rows.last().is_some_and(String::is_empty) {
+            rows.pop();
+        }
+        rows.join("\n")
+    }
+
+    /// Cursor position, 1-based like `cursor::Goto`
+    pub fn cursor(&self) -> (u16, u16) {
+        let col = self.cursor.0.min(self.width - 1);
+        (col as u16 + 1, self.cursor.1 as u16 + 1)
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    pub fn bracketed_paste(&self) -> bool {
+        self.bracketed_paste
+    }
+
+    pub fn alternate_screen(&self) -> bool {
+        self.main.is_some()
+    }
+
+    /// Style of a cell, 1-based like `cursor::Goto`
+    pub fn style_at(&self, x: u16, y: u16) -> Style {
+        self.cells[y as usize - 1][x as usize - 1].style
+    }
+
+    /// Position of the first occurrence of some text, 1-based like `cursor::Goto`
+    pub fn find(&self, text: &str) -> Option<(u16, u16)> {
+        self.rows().iter().enumerate().find_map(|(y, row)| {
+            let index = row.find(text)?;
+            let x: usize = row[..index].chars().map(|c| c.width().unwrap_or(0)).sum();
+            Some((x as u16 + 1, y as u16 + 1))
+        })
+    }
+
+    /// Compare the screen text with `tests/fixtures/screens/<name>.txt`.
+    /// Run the tests with `UPDATE_SNAPSHOTS=1` to write the file instead.
+    pub fn assert_snapshot(&self, name: &str) {
+        let path = PathBuf::from(SNAPSHOT_DIR).join(format!("{}.txt", name));
+        let actual = format!("{}\n", self.text());
+
+        if std::env::var_os(UPDATE_SNAPSHOTS).is_some() {
+            fs::create_dir_all(SNAPSHOT_DIR).unwrap();
+            fs::write(&path, actual).unwrap();
+            return;
+        }
+
+        let expected = fs::read_to_string(&path).unwrap_or_else(|err| {
+            panic!(
+                "{}: {} (run with {}=1 to create it)",
+                path.display(),
+                err,
+                UPDATE_SNAPSHOTS
+            )
+        });
+        assert_eq!(actual, expected, "screen does not match {}", path.display());
+    }
+
+    /*
+     * Interpret one char written to the terminal.
+     */
+    fn feed(&mut self, c: char) {
+        match std::mem::replace(&mut self.state, State::Ground) {
+            State::Ground => match c {
+                '\x1b' => self.state = State::Escape,
+                '\r' => self.cursor.0 = 0,
+                '\n' => self.line_feed(),
+                '\x08' => self.cursor.0 = self.cursor.0.min(self.width - 1).saturating_sub(1),
+                '\t' => self.cursor.0 = ((self.cursor.0 / 8 + 1) * 8).min(self.width - 1),
+                c if c.is_control() => {}
+                c => self.print(c),
+            },
+            State::Escape => match c {
+                '[' => self.state = State::Csi(String::new()),
+                '7' => self.saved_cursor = self.cursor,
+                '8' => self.cursor = self.saved_cursor,
+                _ => {}
+            },
+            State::Csi(mut params) => {
+                if ('\x20'..='\x3f').contains(&c) {
+                    params.push(c);
+                    self.state = State::Csi(params);
+                } else {
+                    self.control_sequence(&params, c);
+                }
+            }
+        }
+    }
+
+    /*
+     * Run a control sequence `ESC [ params final`.
+     */
+    fn control_sequence(&mut self, params: &str, final_byte: char) {
+        if let Some(modes) = params.strip_prefix('?') {
+            for mode in modes.split(';') {
+                self.set_mode(mode, final_byte == 'h');
+            }
+            return;
+        }
+
+        let args: Vec<usize> = params
+            .split(';')
+            .map(|arg| arg.parse().unwrap_or(0))
+            .collect();
+        let arg = |index: usize, default: usize| match args.get(index) {
+            Some(0) | None => default,
+            Some(&value) => value,
+        };
+        let (col, row) = (self.cursor.0.min(self.width - 1), self.cursor.1);
+
+        match final_byte {
+            'H' | 'f' => {
+                self.cursor = (
+                    (arg(1, 1) - 1).min(self.width - 1),
+                    (arg(0, 1) - 1).min(self.height - 1),
+                )
+            }
+            'A' => self.cursor = (col, row.saturating_sub(arg(0, 1))),
+            'B' => self.cursor = (col, (row + arg(0, 1)).min(self.height - 1)),
+            'C' => self.cursor = ((col + arg(0, 1)).min(self.width - 1), row),
+            'D' => self.cursor = (col.saturating_sub(arg(0, 1)), row),
+            'G' => self.cursor = ((arg(0, 1) - 1).min(self.width - 1), row),
+            'J' => match args[0] {
+                0 => {
+                    self.erase(row, col..self.width);
+                    for below in row + 1..self.height {
+                        self.erase(below, 0..self.width);
+                    }
+                }
+                1 => {
+                    for above in 0..row {
+                        self.erase(above, 0..self.width);
+                    }
+                    self.erase(row, 0..col + 1);
+                }
+                _ => {
+                    for any in 0..self.height {
+                        self.erase(any, 0..self.width);
+                    }
+                }
+            },
+            'K' => match args[0] {
+                0 => self.erase(row, col..self.width),
+                1 => self.erase(row, 0..col + 1),
+                _ => self.erase(row, 0..self.width),
+            },
+            'm' => self.select_graphic_rendition(&args),
+            's' => self.saved_cursor = self.cursor,
+            'u' => self.cursor = self.saved_cursor,
+            _ => {}
+        }
+    }
+
+    /*
+     * Switch a private mode such as `?25h` (show cursor) on or off.
+     */
+    fn set_mode(&mut self, mode: &str, on: bool) {
+        match mode {
+            "25" => self.cursor_visible = on,
+            "2004" => self.bracketed_paste = on,
+            "1049" if on && self.main.is_none() => {
+                let blank = vec![vec![Cell::default(); self.width]; self.height];
+                self.main = Some(std::mem::replace(&mut self.cells, blank));
+                self.saved_cursor = self.cursor;
+            }
+            "1049" if !on => {
+                if let Some(main) = self.main.take() {
+                    self.cells = main;
+                    self.cursor = self.saved_cursor;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /*
+     * Update the current style from SGR parameters, e.g. `38;5;1` for a red foreground.
+     */
+    fn select_graphic_rendition(&mut self, args: &[usize]) {
+        let mut args = args.iter().copied();
+        while let Some(arg) = args.next() {
+            let style = &mut self.style;
+            match arg {
+                0 => *style = Style::default(),
+                1 => style.bold = true,
+                2 => style.faint = true,
+                3 => style.italic = true,
+                4 => style.underline = true,
+                7 => style.inverse = true,
+                9 => style.crossed_out = true,
+                21 => style.bold = false,
+                22 => (style.bold, style.faint) = (false, false),
+                23 => style.italic = false,
+                24 => style.underline = false,
+                27 => style.inverse = false,
+                29 => style.crossed_out = false,
+                30..=37 => style.fg = Some(Color::Indexed((arg - 30) as u8)),
+                38 => style.fg = extended_color(&mut args),
+                39 => style.fg = None,
+                40..=47 => style.bg = Some(Color::Indexed((arg - 40) as u8)),
+                48 => style.bg = extended_color(&mut args),
+                49 => style.bg = None,
+                90..=97 => style.fg = Some(Color::Indexed((arg - 90 + 8) as u8)),
+                100..=107 => style.bg = Some(Color::Indexed((arg - 100 + 8) as u8)),
+                _ => {}
+            }
+        }
+    }
+
+    /*
+     * Put a char at the cursor, wrapping to the next row at the right edge.
+     */
+    fn print(&mut self, c: char) {
+        let width = c.width().unwrap_or(0);
+        if width == 0 {
+            // Combining chars join the char before the cursor
+            let (col, row) = self.cursor;
+            if let Some(cell) = col
+                .checked_sub(1)
+                .and_then(|col| self.cells[row].get_mut(col))
+            {
+                cell.symbol.push(c);
+            }
+            return;
+        }
+
+        if self.cursor.0 + width > self.width {
+            self.cursor.0 = 0;
+            self.line_feed();
+        }
+        let (col, row) = self.cursor;
+        self.erase(row, col..col + width);
+        self.cells[row][col] = Cell {
+            symbol: c.to_string(),
+            style: self.style,
+            continuation: false,
+        };
+        for half in col + 1..col + width {
+            self.cells[row][half] = Cell {
+                symbol: String::new(),
+                style: self.style,
+                continuation: true,
+            };
+        }
+        self.cursor.0 += width;
+    }
+
+    /*
+     * Blank cells of a row, including both halves of wide chars cut by the range.
+     */
+    fn erase(&mut self, row: usize, cols: std::ops::Range<usize>) {
+        let cells = &mut self.cells[row];
+        let mut start = cols.start.min(self.width);
+        let mut end = cols.end.min(self.width);
+        while start > 0 && start < self.width && cells[start].continuation {
+            start -= 1;
+        }
+        while end < self.width && cells[end].continuation {
+            end += 1;
+        }
+        for cell in &mut cells[start..end] {
+            *cell = Cell::default();
+        }
+    }
+
+    /*
+     * Move the cursor down a row, scrolling the screen up at the bottom.
+     */
+    fn line_feed(&mut self) {
+        if self.cursor.1 + 1 < self.height {
+            self.cursor.1 += 1;
+        } else {
+            self.cells.remove(0);
+            self.cells.push(vec![Cell::default(); self.width]);
+        }
+    }
+}
+
+/*
+ * Read the rest of a `38;5;n` or `38;2;r;g;b` color.
+ */
+fn extended_color(args: &mut impl Iterator<Item = usize>) -> Option<Color> {
+    match args.next() {
+        Some(5) => args.next().map(|index| Color::Indexed(index as u8)),
+        Some(2) => {
+            let mut channel = || args.next().unwrap_or(0) as u8;
+            Some(Color::Rgb(channel(), channel(), channel()))
+        }
+        _ => None,
+    }
+}
+
+impl Write for Screen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text.len(),
+            // The rest of a char may come with the next write
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        };
+
+        let bytes: Vec<u8> = self.pending.drain(..valid).collect();
+        for c in String::from_utf8_lossy(&bytes).chars() {
+            self.feed(c);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use termion::{clear, color, cursor, screen, style};
+
+    #[test]
+    fn it_moves_the_cursor_and_clears() {
+        let mut screen = Screen::new(10, 4);
+        write!(screen, "hello\r\nworld{}x", cursor::Goto(3, 4)).unwrap();
+        assert_eq!(screen.rows(), vec!["hello", "world", "", "  x"]);
+        assert_eq!(screen.cursor(), (4, 4));
+
+        write!(screen, "{}{}", cursor::Goto(3, 1), clear::UntilNewline).unwrap();
+        assert_eq!(screen.row(1), "he");
+        write!(screen, "{}{}", cursor::Goto(2, 2), clear::AfterCursor).unwrap();
+        assert_eq!(screen.text(), "he\nw");
+        write!(screen, "{}", clear::All).unwrap();
+        assert_eq!(screen.text(), "");
+    }
+
+    #[test]
+    fn it_wraps_and_scrolls() {
+        let mut screen = Screen::new(4, 2);
+        write!(screen, "abcdef").unwrap();
+        assert_eq!(screen.rows(), vec!["abcd", "ef"]);
+        write!(screen, "\r\nghij").unwrap();
+        assert_eq!(screen.rows(), vec!["ef", "ghij"]);
+        assert_eq!(screen.cursor(), (4, 2));
+    }
+
+    #[test]
+    fn it_keeps_wide_chars_whole() {
+        let mut screen = Screen::new(5, 2);
+        write!(screen, "a日本").unwrap();
+        assert_eq!(screen.rows(), vec!["a日本", ""]);
+        assert_eq!(screen.find("本"), Some((4, 1)));
+
+        // Overwriting half of a wide char blanks the other half
+        write!(screen, "{}x", cursor::Goto(3, 1)).unwrap();
+        assert_eq!(screen.row(1), "a x本");
+        // A wide char that does not fit moves to the next row
+        write!(screen, "{}日", cursor::Goto(5, 1)).unwrap();
+        assert_eq!(screen.rows(), vec!["a x本", "日"]);
+    }
+
+    #[test]
+    fn it_tracks_styles_and_modes() {
+        let mut screen = Screen::new(10, 2);
+        write!(
+            screen,
+            "{}{}{}a{}b{}{}c",
+            color::Fg(color::Black),
+            color::Bg(color::White),
+            style::Bold,
+            style::NoBold,
+            style::Reset,
+            color::Fg(color::Rgb(1, 2, 3)),
+        )
+        .unwrap();
+        assert_eq!(
+            screen.style_at(1, 1),
+            Style {
+                fg: Some(Color::Indexed(0)),
+                bg: Some(Color::Indexed(7)),
+                bold: true,
+                ..Style::default()
+            }
+        );
+        assert!(!screen.style_at(2, 1).bold);
+        assert_eq!(screen.style_at(3, 1).fg, Some(Color::Rgb(1, 2, 3)));
+        assert_eq!(screen.style_at(3, 1).bg, None);
+
+        write!(screen, "{}{}", cursor::Hide, screen::ToAlternateScreen).unwrap();
+        assert!(!screen.cursor_visible());
+        assert!(screen.alternate_screen());
+        assert_eq!(screen.text(), "");
+        write!(screen, "{}{}", cursor::Show, screen::ToMainScreen).unwrap();
+        assert!(screen.cursor_visible());
+        assert_eq!(screen.text(), "abc");
+    }
+}
@@ -8,11 +8,15 @@ const BAR: &str = "|";
 const WHITESPACE: &str = " ";
 const HYPHEN: &str = "-";
 const USAGE_TABLE: &str = "Select by ← ↓ ↑ → or h j k l, and Enter.";
+const USAGE_TABLE_MULTI: &str = "Select by ← ↓ ↑ → or h j k l, toggle by Space, and Enter.";
 
 pub struct Table {
     name: String,
     data: Vec<String>,
     max_col_size: usize,
+    multi_select: bool,
+    /// Width in columns, or the terminal width if None
+    width: Option<usize>,
 }
 
 pub(crate) fn horizontal_rule(size: usize) -> String {
@@ -22,7 +26,7 @@ pub(crate) fn horizontal_rule(size: usize) -> String {
 /*
  * Get terminal window size.
  */
-fn term_size() -> (u16, u16) {
+pub(crate) fn term_size() -> (u16, u16) {
     match terminal_size() {
         Ok((width, height)) => (width, height),
         _ => (100, 100),
@@ -35,11 +39,30 @@ impl Table {
             name,
             data,
             max_col_size,
+            multi_select: false,
+            width: None,
         }
     }
 
+    /// Show the usage for toggling multiple cells with Space
+    pub fn with_multi_select(mut self, multi_select: bool) -> Table {
+        self.multi_select = multi_select;
+        self
+    }
+
+    /// Lay out columns for a fixed width instead of the terminal width
+    pub fn with_width(mut self, width: usize) -> Table {
+        self.width = Some(width);
+        self
+    }
+
+    pub fn max_col_size(&self) -> usize {
+        self.max_col_size
+    }
+
     pub fn col_count(&self) -> usize {
-        term_size().0 as usize / (self.max_col_size + 2)
+        let width = self.width.unwrap_or_else(|| term_size().0 as usize);
+        width / (self.max_col_size + 2)
     }
 
     pub fn chunked_data(&self) -> Vec<Vec<String>> {
@@ -53,6 +76,13 @@ impl Table {
      * Print data as table.
      */
     pub fn draw(&self, stdout: &mut dyn Write, selected: &str) {
+        self.draw_marked(stdout, selected, &[]);
+    }
+
+    /*
+     * Print data as table, highlighting marked cells in addition to the selected one.
+     */
+    pub fn draw_marked(&self, stdout: &mut dyn Write, selected: &str, marked: &[String]) {
         write!(
             stdout,
             "{}{}",
@@ -68,7 +98,7 @@ impl Table {
                 (
                     names.len() * (self.max_col_size + 2) - 1,
                     names
-                        .into_iter()
+                        .iter()
                         .map(|cell| {
                             // Highlight selected channel
                             let (fg_color, bg_color) = if cell == selected {
@@ -76,6 +106,11 @@ impl Table {
                                     color::Fg(color::Black).to_string(),
                                     color::Bg(color::White).to_string(),
                                 )
+                            } else if marked.contains(cell) {
+                                (
+                                    color::Fg(color::Black).to_string(),
+                                    color::Bg(color::Green).to_string(),
+                                )
                             } else {
                                 (
                                     color::Fg(color::Reset).to_string(),
@@ -106,7 +141,12 @@ impl Table {
             Self::print_row(stdout, &row.1.join(BAR));
             Self::print_row(stdout, &horizontal_rule(row.0));
         }
-        write!(stdout, "{}", USAGE_TABLE).unwrap();
+        let usage = if self.multi_select {
+            USAGE_TABLE_MULTI
+        } else {
+            USAGE_TABLE
+        };
+        write!(stdout, "{}", usage).unwrap();
         stdout.flush().unwrap()
     }
 
@@ -114,7 +154,7 @@ impl Table {
      * Print table row with bar.
      */
     fn print_row(stdout: &mut dyn Write, content: &str) {
-        write!(stdout, "{}{}{}{}", BAR, content, BAR, "\r\n").unwrap();
+        write!(stdout, "{}{}{}\r\n", BAR, content, BAR).unwrap();
     }
 
     /*
@@ -123,7 +163,7 @@ impl Table {
     fn print_head_channels(&self, stdout: &mut dyn Write, size: usize) {
         let margin = size - self.name.len();
         let margin_left = margin / 2;
-        let margin_right = if margin % 2 == 0 {
+        let margin_right = if margin.is_multiple_of(2) {
             margin_left
         } else {
             margin_left + 1
@@ -146,6 +186,7 @@ impl Table {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::console::screen::{Color, Screen};
 
     #[test]
     fn build_table() {
@@ -161,6 +202,13 @@ mod tests {
         assert_eq!(table.max_col_size, 1);
     }
 
+    #[test]
+    fn with_multi_select_sets_flag() {
+        let table = Table::new("test".to_string(), Vec::new(), 1);
+        assert!(!table.multi_select);
+        assert!(table.with_multi_select(true).multi_select);
+    }
+
     #[test]
     fn horizontal_rule_with_size() {
         let size = 5;
@@ -168,4 +216,24 @@ mod tests {
         let expected = "-----".to_string();
         assert_eq!(actual, expected)
     }
+
+    #[test]
+    fn draw_marked_highlights_cells() {
+        let data = ["general", "random", "deploys", "alerts", "日本語"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        let table = Table::new("CHANNELS".to_string(), data, 7).with_width(30);
+        assert_eq!(table.width, Some(30));
+        let mut screen = Screen::new(30, 12);
+        table.draw_marked(&mut screen, "random", &["alerts".to_string()]);
+        screen.assert_snapshot("table");
+
+        let (x, y) = screen.find("random").unwrap();
+        assert_eq!(screen.style_at(x, y).bg, Some(Color::Indexed(7)));
+        let (x, y) = screen.find("alerts").unwrap();
+        assert_eq!(screen.style_at(x, y).bg, Some(Color::Indexed(2)));
+        let (x, y) = screen.find("general").unwrap();
+        assert_eq!(screen.style_at(x, y).bg, None);
+    }
 }
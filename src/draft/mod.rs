@@ -0,0 +1,152 @@
+use std::fs::{self, DirBuilder, OpenOptions, Permissions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::config;
+
+const DRAFTS_DIR: &str = "drafts";
+
+/// Unsent messages saved per channel so they survive crashes and Ctrl-C
+#[derive(Clone)]
+pub struct DraftStore {
+    dir: PathBuf,
+}
+
+impl DraftStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Drafts in the user's state directory, e.g. `~/.local/state/rslack/drafts`
+    pub fn open_default() -> Result<Self> {
+        Ok(Self::new(config::state_dir()?.join(DRAFTS_DIR)))
+    }
+
+    /// Key of the draft for a message to the given channels
+    pub fn key<S: AsRef<str>>(channel_ids: &[S]) -> String {
+        let mut ids: Vec<&str> = channel_ids.iter().map(AsRef::as_ref).collect();
+        ids.sort_unstable();
+        ids.join("+")
+    }
+
+    /*
+     * Draft file of a key. Keys are channel IDs, so anything else is replaced.
+     */
+    fn path(&self, key: &str) -> PathBuf {
+        let file_name: String = key
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '+' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("{}.txt", file_name))
+    }
+
+    /// Saved draft, if there is one
+    pub fn load(&self, key: &str) -> Option<String> {
+        fs::read_to_string(self.path(key))
+            .ok()
+            .filter(|text| !text.trim().is_empty())
+    }
+
+    /// Save a draft. Saving blank text deletes the draft.
+    /// Drafts are unsent messages, so only the current user can read them.
+    pub fn save(&self, key: &str, text: &str) -> Result<()> {
+        if text.trim().is_empty() {
+            return self.delete(key);
+        }
+
+        DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&self.dir)?;
+        fs::set_permissions(&self.dir, Permissions::from_mode(0o700))?;
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(self.path(key))?;
+        // Drafts saved before they were private keep their mode when opened
+        file.set_permissions(Permissions::from_mode(0o600))?;
+        file.write_all(text.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn delete(&self, key: &str) -> Result<()> {
+        match fs::remove_file(self.path(key)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(name: &str) -> DraftStore {
+        let dir =
+            std::env::temp_dir().join(format!("rslack-drafts-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        DraftStore::new(dir)
+    }
+
+    #[test]
+    fn key_is_independent_of_channel_order() {
+        assert_eq!(DraftStore::key(&["C002", "C001"]), "C001+C002");
+        assert_eq!(DraftStore::key(&["C001".to_string()]), "C001");
+    }
+
+    #[test]
+    fn save_load_and_delete() {
+        let store = store("roundtrip");
+        assert_eq!(store.load("C001"), None);
+
+        store.save("C001", "hello\nworld").unwrap();
+        assert_eq!(store.load("C001"), Some("hello\nworld".to_string()));
+        assert_eq!(store.load("C002"), None);
+
+        store.delete("C001").unwrap();
+        assert_eq!(store.load("C001"), None);
+        store.delete("C001").unwrap();
+    }
+
+    #[test]
+    fn saving_blank_text_deletes_draft() {
+        let store = store("blank");
+        store.save("C001", "hello").unwrap();
+        store.save("C001", "  ").unwrap();
+        assert_eq!(store.load("C001"), None);
+    }
+
+    #[test]
+    fn drafts_are_private() {
+        let store = store("private");
+        fs::create_dir_all(&store.dir).unwrap();
+        fs::write(store.path("C001"), "old").unwrap();
+        fs::set_permissions(store.path("C001"), Permissions::from_mode(0o644)).unwrap();
+
+        store.save("C001", "secret").unwrap();
+        store.save("C002", "secret").unwrap();
+        let mode = |path: &PathBuf| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&store.dir), 0o700);
+        assert_eq!(mode(&store.path("C001")), 0o600);
+        assert_eq!(mode(&store.path("C002")), 0o600);
+        assert_eq!(store.load("C001"), Some("secret".to_string()));
+    }
+
+    #[test]
+    fn path_stays_inside_the_store() {
+        let store = store("path");
+        assert_eq!(store.path("../C001"), store.dir.join("___C001.txt"));
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::console::format_timestamp_simple;
+use crate::slack::{SlackMessage, SlackUsers};
+
+const CSV_HEADER: &str = "ts,time,user_id,user,thread_ts,text";
+
+/// Output format of an exported transcript
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Csv,
+}
+
+/// A message with its author resolved, and its thread replies if exported
+#[derive(Serialize)]
+pub struct ExportedMessage {
+    pub ts: String,
+    pub time: String,
+    pub user_id: Option<String>,
+    pub user: String,
+    pub thread_ts: Option<String>,
+    pub text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub replies: Vec<ExportedMessage>,
+}
+
+/// Channel history in chronological order
+#[derive(Serialize)]
+pub struct Transcript {
+    pub channel: String,
+    pub messages: Vec<ExportedMessage>,
+}
+
+impl ExportedMessage {
+    fn new(message: &SlackMessage, users: &SlackUsers) -> Self {
+        let user = match &message.user {
+            Some(id) => users.name_of(id).to_string(),
+            None => "unknown".to_string(),
+        };
+
+        Self {
+            ts: message.ts.clone(),
+            time: format_timestamp_simple(&message.ts),
+            user_id: message.user.clone(),
+            user,
+            thread_ts: message.thread_ts.clone(),
+            text: resolve_mentions(&message.text, users),
+            replies: Vec::new(),
+        }
+    }
+}
+
+impl Transcript {
+    /// Build a transcript from conversations.history messages (newest first)
+    /// and thread replies keyed by the parent `ts` (parent first, as returned by conversations.replies).
+    pub fn new(
+        channel: &str,
+        messages: &[SlackMessage],
+        replies: &HashMap<String, Vec<SlackMessage>>,
+        users: &SlackUsers,
+    ) -> Self {
+        let messages = messages
+            .iter()
+            .rev()
+            .map(|message| {
+                let mut exported = ExportedMessage::new(message, users);
+                if let Some(thread) = replies.get(&message.ts) {
+                    exported.replies = thread
+                        .iter()
+                        .filter(|reply| reply.ts != message.ts)
+                        .map(|reply| ExportedMessage::new(reply, users))
+                        .collect();
+                }
+                exported
+            })
+            .collect();
+
+        Self {
+            channel: channel.to_string(),
+            messages,
+        }
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W, format: ExportFormat) -> Result<()> {
+        match format {
+            ExportFormat::Markdown => self.write_markdown(out),
+            ExportFormat::Json => {
+                serde_json::to_writer_pretty(&mut *out, self)?;
+                writeln!(out)?;
+                Ok(())
+            }
+            ExportFormat::Csv => self.write_csv(out),
+        }
+    }
+
+    fn write_markdown<W: Write>(&self, out: &mut W) -> Result<()> {
+        writeln!(out, "# #{}", self.channel)?;
+
+        for message in &self.messages {
+            writeln!(out)?;
+            writeln!(out, "**{}** _{}_", message.user, message.time)?;
+            writeln!(out)?;
+            for line in message.text.lines() {
+                writeln!(out, "{}", line)?;
+            }
+
+            for reply in &message.replies {
+                writeln!(out, ">")?;
+                writeln!(out, "> **{}** _{}_", reply.user, reply.time)?;
+                for line in reply.text.lines() {
+                    writeln!(out, "> {}", line)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut W) -> Result<()> {
+        writeln!(out, "{}", CSV_HEADER)?;
+
+        let rows = self
+            .messages
+            .iter()
+            .flat_map(|message| std::iter::once(message).chain(message.replies.iter()));
+        for message in rows {
+            let fields = [
+                message.ts.as_str(),
+                message.time.as_str(),
+                message.user_id.as_deref().unwrap_or_default(),
+                message.user.as_str(),
+                message.thread_ts.as_deref().unwrap_or_default(),
+                message.text.as_str(),
+            ];
+            let row = fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>();
+            writeln!(out, "{}", row.join(","))?;
+        }
+
+        Ok(())
+    }
+}
+
+/*
+ * Replace user mentions (`<@U0123456789>`) with `@display name`.
+ */
+fn resolve_mentions(text: &str, users: &SlackUsers) -> String {
+    let mut resolved = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("<@") {
+        resolved.push_str(&rest[..start]);
+        let mention = &rest[start + 2..];
+        match mention.find('>') {
+            Some(end) => {
+                let id = mention[..end].split('|').next().unwrap_or_default();
+                resolved.push('@');
+                resolved.push_str(users.name_of(id));
+                rest = &mention[end + 1..];
+            }
+            None => {
+                resolved.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    resolved.push_str(rest);
+
+    resolved
+}
+
+/*
+ * Quote a CSV field when it contains separators, quotes or line breaks.
+ */
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slack::{FakeSlack, HistoryRange, SlackApi, SlackUser};
+
+    fn users() -> SlackUsers {
+        SlackUsers::from(vec![SlackUser {
+            id: "U0123456789".to_string(),
+            name: "alice".to_string(),
+            real_name: None,
+            profile: None,
+        }])
+    }
+
+    fn message(ts: &str, text: &str, thread_ts: Option<&str>) -> SlackMessage {
+        SlackMessage {
+            text: text.to_string(),
+            user: Some("U0123456789".to_string()),
+            ts: ts.to_string(),
+            thread_ts: thread_ts.map(str::to_string),
+            reply_count: None,
+        }
+    }
+
+    fn transcript() -> Transcript {
+        let history = vec![
+            message("1700000200.000200", "second, with \"quotes\"", None),
+            message("1700000100.000100", "first", Some("1700000100.000100")),
+        ];
+        let replies = HashMap::from([(
+            "1700000100.000100".to_string(),
+            vec![
+                message("1700000100.000100", "first", Some("1700000100.000100")),
+                message(
+                    "1700000150.000150",
+                    "reply to <@U0123456789>",
+                    Some("1700000100.000100"),
+                ),
+            ],
+        )]);
+        Transcript::new("incident", &history, &replies, &users())
+    }
+
+    #[test]
+    fn transcript_is_chronological_with_replies() {
+        let transcript = transcript();
+        assert_eq!(transcript.messages.len(), 2);
+        assert_eq!(transcript.messages[0].text, "first");
+        assert_eq!(transcript.messages[0].replies.len(), 1);
+        assert_eq!(transcript.messages[0].replies[0].text, "reply to @alice");
+    }
+
+    #[test]
+    fn write_csv_quotes_fields() {
+        let mut out = Vec::new();
+        transcript().write(&mut out, ExportFormat::Csv).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines.len(), 4);
+        assert!(lines[3].ends_with(",\"second, with \"\"quotes\"\"\""));
+    }
+
+    #[test]
+    fn write_markdown_quotes_replies() {
+        let mut out = Vec::new();
+        transcript()
+            .write(&mut out, ExportFormat::Markdown)
+            .unwrap();
+        let markdown = String::from_utf8(out).unwrap();
+        assert!(markdown.starts_with("# #incident\n"));
+        assert!(markdown.contains("> reply to @alice\n"));
+    }
+
+    #[test]
+    fn write_json() {
+        let mut out = Vec::new();
+        transcript().write(&mut out, ExportFormat::Json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["channel"], "incident");
+        assert_eq!(json["messages"][0]["replies"][0]["user"], "alice");
+    }
+
+    #[tokio::test]
+    async fn transcript_of_fake_history_is_oldest_first() {
+        let slack = FakeSlack::new()
+            .with_channel("C001", "incident")
+            .with_message("C001", "U0123456789", "first")
+            .with_message("C001", "U0123456789", "second")
+            .with_message("C001", "U0123456789", "third");
+        let history = slack
+            .get_history("C001", &HistoryRange::default())
+            .await
+            .unwrap();
+
+        let transcript = Transcript::new("incident", &history.messages, &HashMap::new(), &users());
+        let texts: Vec<&str> = transcript
+            .messages
+            .iter()
+            .map(|message| message.text.as_str())
+            .collect();
+        assert_eq!(texts, vec!["first", "second", "third"]);
+    }
+
+    #[test]
+    fn csv_field_plain() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a\nb"), "\"a\nb\"");
+    }
+}
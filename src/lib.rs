@@ -1,4 +1,11 @@
 pub mod config;
 pub mod console;
+pub mod draft;
+pub mod export;
+pub mod mock;
 pub mod option;
+pub mod outbox;
+pub mod sent;
 pub mod slack;
+pub mod template;
+pub mod timerange;
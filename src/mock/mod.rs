@@ -0,0 +1,486 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::slack::{FakeSlack, HistoryRange, SlackApi, SlackMessage};
+
+/// Messages returned per page when no limit is given, as in Slack
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// Contents of a workspace file for `rslack-mock --workspace`
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Workspace {
+    pub channels: Vec<WorkspaceChannel>,
+    pub users: Vec<WorkspaceUser>,
+    pub emoji: Vec<String>,
+    /// Channel history, oldest first
+    pub messages: Vec<WorkspaceMessage>,
+}
+
+#[derive(Deserialize)]
+pub struct WorkspaceChannel {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct WorkspaceUser {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct WorkspaceMessage {
+    /// Channel ID
+    pub channel: String,
+    /// User ID
+    pub user: String,
+    pub text: String,
+}
+
+impl Workspace {
+    /// Read a workspace from a JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents =
+            fs::read_to_string(path).map_err(|err| anyhow!("{}: {}", path.display(), err))?;
+        serde_json::from_str(&contents).map_err(|err| anyhow!("{}: {}", path.display(), err))
+    }
+
+    /// A small workspace to try rslack with
+    pub fn demo() -> Self {
+        let channel = |id: &str, name: &str| WorkspaceChannel {
+            id: id.to_string(),
+            name: name.to_string(),
+        };
+        let user = |id: &str, name: &str| WorkspaceUser {
+            id: id.to_string(),
+            name: name.to_string(),
+        };
+        let message = |channel: &str, user: &str, text: &str| WorkspaceMessage {
+            channel: channel.to_string(),
+            user: user.to_string(),
+            text: text.to_string(),
+        };
+
+        Self {
+            channels: vec![
+                channel("C0000000001", "general"),
+                channel("C0000000002", "random"),
+                channel("C0000000003", "deploys"),
+            ],
+            users: vec![user("U0000000001", "alice"), user("U0000000002", "bob")],
+            emoji: vec!["partyparrot".to_string()],
+            messages: vec![
+                message(
+                    "C0000000001",
+                    "U0000000001",
+                    "Welcome to the mock workspace!",
+                ),
+                message(
+                    "C0000000001",
+                    "U0000000002",
+                    "Messages posted here stay until the server stops.",
+                ),
+                message(
+                    "C0000000003",
+                    "U0000000001",
+                    "Deploying api v1.2.0 to staging",
+                ),
+            ],
+        }
+    }
+
+    /// Fill an in-memory workspace
+    pub fn into_fake(self) -> FakeSlack {
+        let mut slack = FakeSlack::new();
+        for channel in self.channels {
+            slack = slack.with_channel(&channel.id, &channel.name);
+        }
+        for user in self.users {
+            slack = slack.with_user(&user.id, &user.name);
+        }
+        for emoji in self.emoji {
+            slack = slack.with_emoji(&emoji);
+        }
+        for message in self.messages {
+            slack = slack.with_message(&message.channel, &message.user, &message.text);
+        }
+        slack
+    }
+}
+
+/// Serve the Slack Web API from a fake workspace on 127.0.0.1 with a free port.
+/// Returns the base URL to give to `SlackClient::new`.
+pub async fn start(slack: FakeSlack) -> Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(serve(listener, slack));
+    Ok(url)
+}
+
+/// Answer Slack Web API requests from a fake workspace until the listener fails
+pub async fn serve(listener: TcpListener, slack: FakeSlack) -> Result<()> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let slack = slack.clone();
+        tokio::spawn(async move {
+            if let Err(err) = handle(stream, &slack).await {
+                eprintln!("[Warning] {}: {}", peer, err);
+            }
+        });
+    }
+}
+
+/*
+ * Answer a single HTTP/1.1 request and close the connection.
+ */
+async fn handle(stream: TcpStream, slack: &FakeSlack) -> Result<()> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let target = request_line
+        .split_whitespace()
+        .nth(1)
+        .ok_or_else(|| anyhow!("Malformed request: {}", request_line.trim()))?
+        .to_string();
+
+    let mut content_length = 0;
+    let mut authorized = false;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => content_length = value.trim().parse()?,
+                "authorization" => authorized = value.trim().starts_with("Bearer "),
+                _ => {}
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+
+    // Slack takes arguments from the query string and from form bodies alike
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+    let params: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes())
+        .chain(url::form_urlencoded::parse(&body))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+
+    let (status, response) = match path.strip_prefix("/api/") {
+        Some(_) if !authorized => ("200 OK", error("not_authed")),
+        Some(method) => ("200 OK", call(slack, method, &params).await),
+        None => ("404 Not Found", error("not_found")),
+    };
+
+    let response = response.to_string();
+    let mut stream = reader.into_inner();
+    stream
+        .write_all(
+            format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                response.len(),
+                response
+            )
+            .as_bytes(),
+        )
+        .await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/*
+ * Run a Web API method against the workspace and build Slack's JSON response.
+ */
+async fn call(slack: &FakeSlack, method: &str, params: &HashMap<String, String>) -> Value {
+    let param = |name: &str| params.get(name).map(String::as_str).unwrap_or_default();
+    let range = || HistoryRange {
+        oldest: params.get("oldest").cloned(),
+        latest: params.get("latest").cloned(),
+        inclusive: matches!(param("inclusive"), "true" | "1"),
+    };
+
+    let response = match method {
+        "conversations.list" => slack
+            .get_channels()
+            .await
+            .map(|channels| json!({ "channels": channels.channels })),
+        "conversations.info" => slack
+            .get_channel_info(param("channel"))
+            .await
+            .map(|channel| json!({ "channel": channel })),
+        "conversations.history" => slack
+            .get_history(param("channel"), &range())
+            .await
+            .and_then(|messages| page(messages.messages, params)),
+        "conversations.replies" => slack
+            .get_replies(param("channel"), param("ts"))
+            .await
+            .and_then(|messages| page(messages.messages, params)),
+        "chat.postMessage" => {
+            let thread_ts = params.get("thread_ts").map(String::as_str);
+            slack
+                .post(param("channel"), param("text"), thread_ts)
+                .map(|ts| json!({ "ts": ts }))
+        }
+        "users.list" => slack
+            .get_users()
+            .await
+            .map(|users| json!({ "members": users.users })),
+        "emoji.list" => slack.get_emoji().await.map(|names| {
+            let emoji: HashMap<String, String> = names
+                .into_iter()
+                .map(|name| {
+                    let url = format!("https://emoji.example.com/{}.png", name);
+                    (name, url)
+                })
+                .collect();
+            json!({ "emoji": emoji })
+        }),
+        _ => Err(anyhow!("unknown_method")),
+    };
+
+    match response {
+        Ok(mut value) => {
+            value["ok"] = json!(true);
+            value
+        }
+        Err(err) => error(&err.to_string()),
+    }
+}
+
+/*
+ * Cut a page out of a message list the way Slack's cursor pagination does.
+ * The cursor is the offset of the first message of the page.
+ */
+fn page(messages: Vec<SlackMessage>, params: &HashMap<String, String>) -> Result<Value> {
+    let limit = match params.get("limit").and_then(|limit| limit.parse().ok()) {
+        Some(0) | None => DEFAULT_HISTORY_LIMIT,
+        Some(limit) => limit,
+    };
+    let offset = match params.get("cursor").map(String::as_str) {
+        Some("") | None => 0,
+        Some(cursor) => cursor
+            .parse::<usize>()
+            .ok()
+            .filter(|&offset| offset <= messages.len())
+            .ok_or_else(|| anyhow!("invalid_cursor"))?,
+    };
+
+    let end = (offset + limit).min(messages.len());
+    let has_more = end < messages.len();
+    let next_cursor = if has_more {
+        end.to_string()
+    } else {
+        String::new()
+    };
+    Ok(json!({
+        "messages": messages[offset..end],
+        "has_more": has_more,
+        "response_metadata": { "next_cursor": next_cursor },
+    }))
+}
+
+fn error(code: &str) -> Value {
+    json!({ "ok": false, "error": code })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::slack::{SlackClient, FAKE_BOT_USER};
+    use serial_test::serial;
+
+    async fn client() -> (SlackClient, FakeSlack) {
+        let slack = Workspace::load(Path::new("tests/fixtures/mock/workspace.json"))
+            .unwrap()
+            .into_fake();
+        let url = start(slack.clone()).await.unwrap();
+
+        std::env::set_var("RSLACK_TOKEN", "test-token");
+        let config = Config::new(None).unwrap();
+        (SlackClient::new(&config, &url).unwrap(), slack)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn it_serves_channels_users_and_emoji() {
+        let (client, _) = client().await;
+
+        let channels = client.get_channels().await.unwrap();
+        assert_eq!(channels.channel_names(), vec!["general", "random"]);
+        let channel = client.get_channel_info("C0000000002").await.unwrap();
+        assert_eq!(channel.name, "random");
+        let err = client.get_channel_info("C0000000404").await.unwrap_err();
+        assert_eq!(err.to_string(), "channel_not_found");
+
+        let users = client.get_users().await.unwrap();
+        assert_eq!(users.name_of("U0000000001"), "alice");
+        assert_eq!(client.get_emoji().await.unwrap(), vec!["partyparrot"]);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn it_keeps_posted_messages() {
+        let (client, slack) = client().await;
+
+        client
+            .post_message("C0000000001", "hello & welcome")
+            .await
+            .unwrap();
+        let messages = client
+            .get_messages("C0000000001", 10, &HistoryRange::default())
+            .await
+            .unwrap();
+        assert_eq!(messages.messages.len(), 3);
+        assert_eq!(messages.messages[0].text, "hello & welcome");
+        assert_eq!(messages.messages[0].user.as_deref(), Some(FAKE_BOT_USER));
+
+        let parent = messages.messages[1].ts.clone();
+        client
+            .post_reply("C0000000001", "in thread", Some(&parent))
+            .await
+            .unwrap();
+        let thread = client.get_replies("C0000000001", &parent).await.unwrap();
+        assert_eq!(thread.messages.len(), 2);
+        assert_eq!(slack.messages("C0000000001").len(), 4);
+
+        let err = client.post_message("C0000000404", "hi").await.unwrap_err();
+        assert_eq!(err.to_string(), "channel_not_found");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn it_pages_history_and_replies_with_cursors() {
+        let (client, slack) = client().await;
+        for i in 0..250 {
+            slack
+                .post_message("C0000000002", &i.to_string())
+                .await
+                .unwrap();
+        }
+        let parent = slack.messages("C0000000002")[0].ts.clone();
+        for i in 0..210 {
+            slack
+                .post_reply("C0000000002", &format!("reply {}", i), Some(&parent))
+                .await
+                .unwrap();
+        }
+
+        let history = client
+            .get_history("C0000000002", &HistoryRange::default())
+            .await
+            .unwrap();
+        assert_eq!(history.messages.len(), 250);
+        assert_eq!(history.messages[0].text, "249");
+        assert_eq!(history.messages[249].text, "0");
+
+        let newest = client
+            .get_messages("C0000000002", 220, &HistoryRange::default())
+            .await
+            .unwrap();
+        assert_eq!(newest.messages.len(), 220);
+        assert_eq!(newest.messages[219].text, "30");
+
+        let thread = client.get_replies("C0000000002", &parent).await.unwrap();
+        assert_eq!(thread.messages.len(), 211);
+        assert_eq!(thread.messages[210].text, "reply 209");
+    }
+
+    #[test]
+    fn page_reports_the_remainder() {
+        let messages: Vec<SlackMessage> = Workspace::demo().into_fake().messages("C0000000001");
+        let params = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+
+        let first = page(messages.clone(), &params(&[("limit", "1")])).unwrap();
+        assert_eq!(first["has_more"], json!(true));
+        assert_eq!(first["response_metadata"]["next_cursor"], json!("1"));
+        let last = page(
+            messages.clone(),
+            &params(&[("limit", "1"), ("cursor", "1")]),
+        )
+        .unwrap();
+        assert_eq!(last["has_more"], json!(false));
+        assert_eq!(last["response_metadata"]["next_cursor"], json!(""));
+        let err = page(messages, &params(&[("cursor", "9")])).unwrap_err();
+        assert_eq!(err.to_string(), "invalid_cursor");
+    }
+
+    #[tokio::test]
+    async fn it_returns_the_ts_of_each_posted_message() {
+        let slack = Workspace::demo().into_fake();
+        let url = start(slack.clone()).await.unwrap();
+        let http = reqwest::Client::new();
+
+        let mut posts = tokio::task::JoinSet::new();
+        for i in 0..20 {
+            let (http, url) = (http.clone(), url.clone());
+            posts.spawn(async move {
+                let text = i.to_string();
+                let res: Value = http
+                    .post(format!("{}/api/chat.postMessage", url))
+                    .bearer_auth("token")
+                    .form(&[("channel", "C0000000002"), ("text", text.as_str())])
+                    .send()
+                    .await
+                    .unwrap()
+                    .json()
+                    .await
+                    .unwrap();
+                (text, res["ts"].as_str().unwrap().to_string())
+            });
+        }
+
+        let posted = posts.join_all().await;
+        let messages = slack.messages("C0000000002");
+        for (text, ts) in posted {
+            let message = messages.iter().find(|message| message.ts == ts).unwrap();
+            assert_eq!(message.text, text);
+        }
+    }
+
+    #[tokio::test]
+    async fn it_rejects_unknown_methods_and_missing_tokens() {
+        let url = start(Workspace::demo().into_fake()).await.unwrap();
+        let http = reqwest::Client::new();
+
+        let res: Value = http
+            .get(format!("{}/api/conversations.list", url))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(res, error("not_authed"));
+
+        let res: Value = http
+            .post(format!("{}/api/admin.users.remove", url))
+            .bearer_auth("token")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(res, error("unknown_method"));
+    }
+}
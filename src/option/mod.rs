@@ -1,27 +1,190 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+
+use crate::export::ExportFormat;
+use crate::template::parse_var;
 
 /// Default number of messages to fetch
 pub const DEFAULT_MESSAGE_LIMIT: usize = 10;
 
+/// Default number of search results to fetch
+pub const DEFAULT_SEARCH_LIMIT: usize = 20;
+
 #[derive(Parser)]
 #[cfg_attr(test, derive(Debug, PartialEq))]
 #[command(author, version, about, long_about = None)]
 pub struct Opt {
-    /// Slack channel name or ID
-    #[arg(short, long)]
-    pub channel: Option<String>,
+    /// Slack channel name or ID (repeat or separate with commas to post to several channels)
+    #[arg(short, long, value_delimiter = ',')]
+    pub channel: Vec<String>,
 
     /// Message to post
     #[arg(short, long)]
     pub message: Option<String>,
 
+    /// Compose the message in $VISUAL / $EDITOR
+    #[arg(short, long)]
+    pub editor: bool,
+
+    /// Post without asking for confirmation (protected channels, @channel/@here, split messages)
+    #[arg(short, long, conflicts_with = "read")]
+    pub yes: bool,
+
+    /// Print the API request that would post the message instead of posting it
+    #[arg(long, conflicts_with = "read")]
+    pub dry_run: bool,
+
+    /// Post as a reply in the thread of this message timestamp (e.g. 1700000000.000100)
+    #[arg(long, value_name = "TS", conflicts_with = "read")]
+    pub thread: Option<String>,
+
     /// Read messages from channel instead of posting
     #[arg(short, long)]
     pub read: bool,
 
-    /// Number of messages to fetch (default: 10)
+    /// Number of messages to fetch (default: 10, or all messages in --since range)
     #[arg(short, long)]
     pub limit: Option<usize>,
+
+    /// Read messages posted at or after this time (e.g. 2h, yesterday, 2026-10-01)
+    #[arg(long, value_name = "TIME", requires = "read")]
+    pub since: Option<String>,
+
+    /// Read messages posted before this time (e.g. 30m, today, 2026-10-01)
+    #[arg(long, value_name = "TIME", requires = "read")]
+    pub until: Option<String>,
+
+    /// Include messages exactly at --since or --until
+    #[arg(long, requires = "read")]
+    pub inclusive: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub enum Command {
+    /// Post a message from a template in the config file
+    Post(PostArgs),
+    /// Search messages (search.messages)
+    Search(SearchArgs),
+    /// Export channel history to Markdown, JSON or CSV
+    Export(ExportArgs),
+    /// Manage posts queued while Slack could not be reached
+    Outbox(OutboxArgs),
+    /// Full-screen interface with channels, history and composer
+    Tui,
+}
+
+#[derive(Args)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct OutboxArgs {
+    #[command(subcommand)]
+    pub command: OutboxCommand,
+}
+
+#[derive(Subcommand)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub enum OutboxCommand {
+    /// List queued posts
+    List,
+    /// Post queued posts now, oldest first
+    Flush,
+    /// Remove queued posts without posting them
+    Drop(DropArgs),
+}
+
+#[derive(Args)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct DropArgs {
+    /// Number of the post in `rslack outbox list`
+    #[arg(required_unless_present = "all", conflicts_with = "all")]
+    pub number: Option<usize>,
+
+    /// Remove every queued post
+    #[arg(long)]
+    pub all: bool,
+}
+
+#[derive(Args)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct PostArgs {
+    /// Template name from the [templates] section of the config file
+    #[arg(short, long)]
+    pub template: String,
+
+    /// Template variable (repeat for several variables; missing ones are asked for)
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_var)]
+    pub vars: Vec<(String, String)>,
+
+    /// Slack channel name or ID (repeat or separate with commas to post to several channels)
+    #[arg(short, long, value_delimiter = ',')]
+    pub channel: Vec<String>,
+
+    /// Post without asking for confirmation (protected channels, @channel/@here, split messages)
+    #[arg(short, long)]
+    pub yes: bool,
+
+    /// Print the API request that would post the message instead of posting it
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct SearchArgs {
+    /// Search query
+    pub query: String,
+
+    /// Only search in this channel
+    #[arg(long = "in", value_name = "CHANNEL")]
+    pub in_channel: Option<String>,
+
+    /// Only search messages from this user
+    #[arg(long, value_name = "USER")]
+    pub from: Option<String>,
+
+    /// Only search messages after this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub after: Option<String>,
+
+    /// Maximum number of results to fetch (default: 20)
+    #[arg(short, long)]
+    pub limit: Option<usize>,
+
+    /// Print results as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct ExportArgs {
+    /// Slack channel name or ID
+    #[arg(short, long)]
+    pub channel: String,
+
+    /// Only export messages posted at or after this time
+    #[arg(long, value_name = "TIME")]
+    pub since: Option<String>,
+
+    /// Only export messages posted before this time
+    #[arg(long, value_name = "TIME")]
+    pub until: Option<String>,
+
+    /// Include thread replies
+    #[arg(short, long)]
+    pub threads: bool,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = ExportFormat::Markdown)]
+    pub format: ExportFormat,
+
+    /// Output file (default: stdout)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
 }
 
 impl Opt {
@@ -38,10 +201,18 @@ mod tests {
     fn argument_with_default() {
         assert_eq!(
             Opt {
-                channel: None,
+                channel: vec![],
                 message: None,
+                editor: false,
+                yes: false,
+                dry_run: false,
+                thread: None,
                 read: false,
                 limit: None,
+                since: None,
+                until: None,
+                inclusive: false,
+                command: None,
             },
             Opt::parse_from::<[&str; 0], &str>([]),
         )
@@ -51,7 +222,7 @@ mod tests {
     fn argument_with_read_flag() {
         let opts = Opt::parse_from(["rslack", "-r", "-c", "general"]);
         assert!(opts.read);
-        assert_eq!(opts.channel, Some("general".to_string()));
+        assert_eq!(opts.channel, vec!["general".to_string()]);
     }
 
     #[test]
@@ -60,4 +231,174 @@ mod tests {
         assert!(opts.read);
         assert_eq!(opts.limit, Some(20));
     }
+
+    #[test]
+    fn argument_with_multiple_channels() {
+        let opts = Opt::parse_from(["rslack", "-c", "general,random", "-c", "dev"]);
+        assert_eq!(
+            opts.channel,
+            vec![
+                "general".to_string(),
+                "random".to_string(),
+                "dev".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn argument_with_post_command() {
+        let opts = Opt::parse_from([
+            "rslack",
+            "post",
+            "--template",
+            "deploy",
+            "--var",
+            "service=api",
+            "--var",
+            "version=v1.2.0",
+            "-c",
+            "deploys",
+        ]);
+        assert_eq!(
+            opts.command,
+            Some(Command::Post(PostArgs {
+                template: "deploy".to_string(),
+                vars: vec![
+                    ("service".to_string(), "api".to_string()),
+                    ("version".to_string(), "v1.2.0".to_string()),
+                ],
+                channel: vec!["deploys".to_string()],
+                yes: false,
+                dry_run: false,
+            }))
+        );
+        assert!(Opt::try_parse_from(["rslack", "post", "-t", "deploy", "--var", "oops"]).is_err());
+    }
+
+    #[test]
+    fn argument_with_search_command() {
+        let opts = Opt::parse_from([
+            "rslack",
+            "search",
+            "deploy",
+            "--in",
+            "#ops",
+            "--from",
+            "@alice",
+            "--after",
+            "2026-10-01",
+            "--json",
+        ]);
+        assert_eq!(
+            opts.command,
+            Some(Command::Search(SearchArgs {
+                query: "deploy".to_string(),
+                in_channel: Some("#ops".to_string()),
+                from: Some("@alice".to_string()),
+                after: Some("2026-10-01".to_string()),
+                limit: None,
+                json: true,
+            }))
+        );
+    }
+
+    #[test]
+    fn argument_with_export_command() {
+        let opts = Opt::parse_from([
+            "rslack",
+            "export",
+            "-c",
+            "incident",
+            "--since",
+            "2026-10-01",
+            "--threads",
+            "-f",
+            "csv",
+            "-o",
+            "incident.csv",
+        ]);
+        assert_eq!(
+            opts.command,
+            Some(Command::Export(ExportArgs {
+                channel: "incident".to_string(),
+                since: Some("2026-10-01".to_string()),
+                until: None,
+                threads: true,
+                format: ExportFormat::Csv,
+                output: Some(PathBuf::from("incident.csv")),
+            }))
+        );
+    }
+
+    #[test]
+    fn argument_with_time_range() {
+        let opts = Opt::parse_from([
+            "rslack",
+            "-r",
+            "-c",
+            "general",
+            "--since",
+            "2h",
+            "--until",
+            "2026-10-01",
+        ]);
+        assert_eq!(opts.since, Some("2h".to_string()));
+        assert_eq!(opts.until, Some("2026-10-01".to_string()));
+        assert!(!opts.inclusive);
+    }
+
+    #[test]
+    fn argument_with_time_range_requires_read() {
+        assert!(Opt::try_parse_from(["rslack", "--since", "2h"]).is_err());
+    }
+
+    #[test]
+    fn argument_with_editor_flag() {
+        let opts = Opt::parse_from(["rslack", "-e", "-c", "general"]);
+        assert!(opts.editor);
+    }
+
+    #[test]
+    fn argument_with_yes_flag() {
+        let opts = Opt::parse_from(["rslack", "-y", "-m", "hello"]);
+        assert!(opts.yes);
+        assert!(Opt::try_parse_from(["rslack", "-y", "-r"]).is_err());
+    }
+
+    #[test]
+    fn argument_with_dry_run_flag() {
+        let opts = Opt::parse_from(["rslack", "--dry-run", "-c", "general", "-m", "hello"]);
+        assert!(opts.dry_run);
+        assert!(Opt::try_parse_from(["rslack", "--dry-run", "-r"]).is_err());
+    }
+
+    #[test]
+    fn argument_with_thread() {
+        let opts = Opt::parse_from(["rslack", "-c", "general", "--thread", "1700000000.000100"]);
+        assert_eq!(opts.thread.as_deref(), Some("1700000000.000100"));
+        assert!(Opt::try_parse_from(["rslack", "-r", "--thread", "1700000000.000100"]).is_err());
+    }
+
+    #[test]
+    fn argument_with_outbox_command() {
+        let opts = Opt::parse_from(["rslack", "outbox", "drop", "2"]);
+        assert_eq!(
+            opts.command,
+            Some(Command::Outbox(OutboxArgs {
+                command: OutboxCommand::Drop(DropArgs {
+                    number: Some(2),
+                    all: false,
+                }),
+            }))
+        );
+        let opts = Opt::parse_from(["rslack", "outbox", "list"]);
+        assert_eq!(
+            opts.command,
+            Some(Command::Outbox(OutboxArgs {
+                command: OutboxCommand::List,
+            }))
+        );
+        assert!(Opt::try_parse_from(["rslack", "outbox", "drop"]).is_err());
+        assert!(Opt::try_parse_from(["rslack", "outbox", "drop", "1", "--all"]).is_err());
+    }
 }
@@ -0,0 +1,265 @@
+use std::fs;
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::slack::{is_transient, SlackApi};
+
+const OUTBOX_FILE: &str = "outbox.jsonl";
+
+/// A post that failed because Slack could not be reached
+#[derive(Clone, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct QueuedMessage {
+    /// Channel ID
+    pub channel: String,
+    /// Channel name at the time the message was queued, for display
+    pub channel_name: String,
+    pub text: String,
+    /// Thread to reply in, if any
+    pub thread_ts: Option<String>,
+    /// Unix time the message was queued at
+    pub queued_at: i64,
+}
+
+impl QueuedMessage {
+    pub fn new(channel: &str, channel_name: &str, text: &str, thread_ts: Option<&str>) -> Self {
+        Self {
+            channel: channel.to_string(),
+            channel_name: channel_name.to_string(),
+            text: text.to_string(),
+            thread_ts: thread_ts.map(str::to_string),
+            queued_at: chrono::Local::now().timestamp(),
+        }
+    }
+}
+
+/// Posts waiting to be retried, oldest first, stored as JSON lines
+pub struct Outbox {
+    path: PathBuf,
+    entries: Vec<QueuedMessage>,
+}
+
+impl Outbox {
+    /// Load the outbox from a file. A missing file is an empty outbox;
+    /// unreadable lines are skipped.
+    pub fn new(path: PathBuf) -> Result<Self> {
+        let entries = match fs::File::open(&path) {
+            Ok(file) => BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .filter_map(|line| serde_json::from_str(&line).ok())
+                .collect(),
+            Err(err) if err.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self { path, entries })
+    }
+
+    /// Outbox in the user's state directory, e.g. `~/.local/state/rslack/outbox.jsonl`
+    pub fn open_default() -> Result<Self> {
+        Self::new(config::state_dir()?.join(OUTBOX_FILE))
+    }
+
+    pub fn entries(&self) -> &[QueuedMessage] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Queue messages to be posted later
+    pub fn push(&mut self, messages: Vec<QueuedMessage>) -> Result<()> {
+        self.entries.extend(messages);
+        self.save()
+    }
+
+    /// Remove a queued message by its position in `entries`
+    pub fn remove(&mut self, index: usize) -> Result<QueuedMessage> {
+        if index >= self.entries.len() {
+            anyhow::bail!("No queued message #{}", index + 1);
+        }
+        let removed = self.entries.remove(index);
+        self.save()?;
+        Ok(removed)
+    }
+
+    /// Remove every queued message
+    pub fn clear(&mut self) -> Result<()> {
+        self.entries.clear();
+        self.save()
+    }
+
+    /// Post the queued messages in order. Posted messages leave the outbox.
+    /// Stops at the first network or server error, keeping that message and
+    /// the ones after it; messages Slack rejects stay queued until dropped.
+    pub async fn flush(
+        &mut self,
+        slack_client: &impl SlackApi,
+    ) -> Result<Vec<(QueuedMessage, Result<()>)>> {
+        let mut results = Vec::new();
+        let mut kept = Vec::new();
+        let mut entries = std::mem::take(&mut self.entries).into_iter();
+
+        for entry in entries.by_ref() {
+            let posted = slack_client
+                .post_reply(&entry.channel, &entry.text, entry.thread_ts.as_deref())
+                .await;
+            let offline = matches!(&posted, Err(err) if is_transient(err));
+            if posted.is_err() {
+                kept.push(entry.clone());
+            }
+            results.push((entry, posted));
+            if offline {
+                break;
+            }
+        }
+
+        kept.extend(entries);
+        self.entries = kept;
+        self.save()?;
+        Ok(results)
+    }
+
+    fn save(&self) -> Result<()> {
+        if self.entries.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+                _ => Ok(()),
+            };
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut contents = String::new();
+        for entry in &self.entries {
+            contents.push_str(&serde_json::to_string(entry)?);
+            contents.push('\n');
+        }
+        fs::write(&self.path, contents)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::slack::SlackClient;
+    use serial_test::serial;
+
+    fn path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "rslack-outbox-{}-{}.jsonl",
+            name,
+            std::process::id()
+        ));
+        fs::remove_file(&path).ok();
+        path
+    }
+
+    fn client(base_url: &str) -> SlackClient {
+        std::env::set_var("RSLACK_TOKEN", "test-token");
+        let config = Config::new(None).unwrap();
+        SlackClient::new(&config, base_url).unwrap()
+    }
+
+    #[test]
+    fn push_remove_and_reload() {
+        let path = path("reload");
+        let mut outbox = Outbox::new(path.clone()).unwrap();
+        outbox
+            .push(vec![
+                QueuedMessage::new("C001", "general", "one", None),
+                QueuedMessage::new("C002", "random", "two", Some("1700000000.000100")),
+            ])
+            .unwrap();
+
+        let mut outbox = Outbox::new(path.clone()).unwrap();
+        assert_eq!(outbox.entries().len(), 2);
+        assert_eq!(
+            outbox.entries()[1].thread_ts.as_deref(),
+            Some("1700000000.000100")
+        );
+
+        assert_eq!(outbox.remove(0).unwrap().text, "one");
+        assert!(outbox.remove(1).is_err());
+        assert_eq!(Outbox::new(path.clone()).unwrap().entries().len(), 1);
+
+        outbox.clear().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn flush_posts_in_order_and_keeps_rejected_messages() {
+        let mut server = mockito::Server::new_async().await;
+        let posted = server
+            .mock("POST", "/api/chat.postMessage")
+            .match_body(mockito::Matcher::UrlEncoded(
+                "channel".to_string(),
+                "C001".to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ok": true}"#)
+            .expect(2)
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/api/chat.postMessage")
+            .match_body(mockito::Matcher::UrlEncoded(
+                "channel".to_string(),
+                "C404".to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ok": false, "error": "channel_not_found"}"#)
+            .create_async()
+            .await;
+
+        let mut outbox = Outbox::new(path("flush")).unwrap();
+        outbox
+            .push(vec![
+                QueuedMessage::new("C001", "general", "one", None),
+                QueuedMessage::new("C404", "gone", "two", None),
+                QueuedMessage::new("C001", "general", "three", None),
+            ])
+            .unwrap();
+
+        let results = outbox.flush(&client(&server.url())).await.unwrap();
+        let texts: Vec<(&str, bool)> = results
+            .iter()
+            .map(|(message, result)| (message.text.as_str(), result.is_ok()))
+            .collect();
+        assert_eq!(texts, vec![("one", true), ("two", false), ("three", true)]);
+        posted.assert_async().await;
+
+        assert_eq!(outbox.entries().len(), 1);
+        assert_eq!(outbox.entries()[0].text, "two");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn flush_stops_when_offline() {
+        let mut outbox = Outbox::new(path("offline")).unwrap();
+        outbox
+            .push(vec![
+                QueuedMessage::new("C001", "general", "one", None),
+                QueuedMessage::new("C002", "random", "two", None),
+            ])
+            .unwrap();
+
+        // Nothing listens on the discard port
+        let results = outbox.flush(&client("http://127.0.0.1:9")).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.is_err());
+        assert_eq!(outbox.entries().len(), 2);
+    }
+}
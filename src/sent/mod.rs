@@ -0,0 +1,177 @@
+use std::fs;
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+const SENT_FILE: &str = "sent.jsonl";
+
+/// A message that was posted successfully
+#[derive(Clone, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct SentMessage {
+    /// Channel ID
+    pub channel: String,
+    pub text: String,
+}
+
+/// Local history of posted messages, oldest first, stored as JSON lines
+pub struct SentHistory {
+    path: PathBuf,
+    size: usize,
+    entries: Vec<SentMessage>,
+}
+
+impl SentHistory {
+    /// Load the history from a file, keeping at most `size` messages.
+    /// A missing file is an empty history; unreadable lines are skipped.
+    pub fn new(path: PathBuf, size: usize) -> Result<Self> {
+        let entries = match fs::File::open(&path) {
+            Ok(file) => BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .filter_map(|line| serde_json::from_str(&line).ok())
+                .collect(),
+            Err(err) if err.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut history = Self {
+            path,
+            size,
+            entries,
+        };
+        history.truncate();
+        Ok(history)
+    }
+
+    /// History in the user's state directory, e.g. `~/.local/state/rslack/sent.jsonl`
+    pub fn open_default(size: usize) -> Result<Self> {
+        Self::new(config::state_dir()?.join(SENT_FILE), size)
+    }
+
+    /// Remember a posted message. Posting the same text to the same channel
+    /// again moves it to the end instead of keeping a duplicate.
+    pub fn record(&mut self, channel_id: &str, text: &str) -> Result<()> {
+        if self.size == 0 || text.trim().is_empty() {
+            return Ok(());
+        }
+
+        self.entries
+            .retain(|entry| !(entry.channel == channel_id && entry.text == text));
+        self.entries.push(SentMessage {
+            channel: channel_id.to_string(),
+            text: text.to_string(),
+        });
+        self.truncate();
+        self.save()
+    }
+
+    /// Texts to recall when writing to the given channels, newest first.
+    /// Messages sent to these channels come before messages sent elsewhere.
+    pub fn recall<S: AsRef<str>>(&self, channel_ids: &[S]) -> Vec<String> {
+        let in_channels =
+            |entry: &&SentMessage| channel_ids.iter().any(|id| id.as_ref() == entry.channel);
+        let (here, elsewhere): (Vec<&SentMessage>, Vec<&SentMessage>) =
+            self.entries.iter().rev().partition(in_channels);
+
+        let mut texts: Vec<String> = Vec::new();
+        for entry in here.into_iter().chain(elsewhere) {
+            if !texts.contains(&entry.text) {
+                texts.push(entry.text.clone());
+            }
+        }
+        texts
+    }
+
+    /*
+     * Drop the oldest messages beyond the configured size.
+     */
+    fn truncate(&mut self) {
+        let excess = self.entries.len().saturating_sub(self.size);
+        self.entries.drain(..excess);
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut contents = String::new();
+        for entry in &self.entries {
+            contents.push_str(&serde_json::to_string(entry)?);
+            contents.push('\n');
+        }
+        fs::write(&self.path, contents)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("rslack-sent-{}-{}.jsonl", name, std::process::id()));
+        fs::remove_file(&path).ok();
+        path
+    }
+
+    #[test]
+    fn record_and_reload() {
+        let path = path("reload");
+        let mut history = SentHistory::new(path.clone(), 10).unwrap();
+        history.record("C001", "deploying api to staging").unwrap();
+        history.record("C002", "lunch?").unwrap();
+
+        let history = SentHistory::new(path, 10).unwrap();
+        assert_eq!(
+            history.entries,
+            vec![
+                SentMessage {
+                    channel: "C001".to_string(),
+                    text: "deploying api to staging".to_string(),
+                },
+                SentMessage {
+                    channel: "C002".to_string(),
+                    text: "lunch?".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn recall_prefers_the_channel_and_skips_duplicates() {
+        let mut history = SentHistory::new(path("recall"), 10).unwrap();
+        history.record("C001", "one").unwrap();
+        history.record("C002", "two").unwrap();
+        history.record("C001", "three").unwrap();
+        history.record("C002", "one").unwrap();
+        history.record("C001", "one").unwrap();
+
+        assert_eq!(history.recall(&["C001"]), vec!["one", "three", "two"]);
+        assert_eq!(history.recall(&["C002"]), vec!["one", "two", "three"]);
+    }
+
+    #[test]
+    fn keeps_at_most_size_messages() {
+        let mut history = SentHistory::new(path("size"), 2).unwrap();
+        for text in ["one", "two", "three"] {
+            history.record("C001", text).unwrap();
+        }
+        assert_eq!(history.recall(&["C001"]), vec!["three", "two"]);
+    }
+
+    #[test]
+    fn size_zero_disables_history() {
+        let path = path("disabled");
+        let mut history = SentHistory::new(path.clone(), 0).unwrap();
+        history.record("C001", "one").unwrap();
+        assert!(history.recall(&["C001"]).is_empty());
+        assert!(!path.exists());
+    }
+}
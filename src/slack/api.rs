@@ -0,0 +1,151 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use super::{
+    ChannelRef, HistoryRange, SearchQuery, SlackChannel, SlackChannels, SlackMessages,
+    SlackSearchMatch, SlackUsers,
+};
+
+/// Slack Web API calls used by the binaries and the console.
+/// `SlackClient` makes them over HTTP and `FakeSlack` answers them in memory.
+pub trait SlackApi: Clone + Send + Sync + 'static {
+    /// Channels the token can see (conversations.list)
+    fn get_channels(&self) -> impl Future<Output = Result<SlackChannels>> + Send;
+
+    /// A single channel, listed or not (conversations.info)
+    fn get_channel_info(
+        &self,
+        channel_id: &str,
+    ) -> impl Future<Output = Result<SlackChannel>> + Send;
+
+    /// Up to `limit` messages of a channel in a range, newest first (conversations.history)
+    fn get_messages(
+        &self,
+        channel_id: &str,
+        limit: usize,
+        range: &HistoryRange,
+    ) -> impl Future<Output = Result<SlackMessages>> + Send;
+
+    /// Every message of a channel in a range, newest first (conversations.history)
+    fn get_history(
+        &self,
+        channel_id: &str,
+        range: &HistoryRange,
+    ) -> impl Future<Output = Result<SlackMessages>> + Send;
+
+    /// A thread's parent message followed by its replies (conversations.replies)
+    fn get_replies(
+        &self,
+        channel_id: &str,
+        thread_ts: &str,
+    ) -> impl Future<Output = Result<SlackMessages>> + Send;
+
+    /// Members of the workspace (users.list)
+    fn get_users(&self) -> impl Future<Output = Result<SlackUsers>> + Send;
+
+    /// Names of the workspace's custom emoji (emoji.list)
+    fn get_emoji(&self) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    /// Post a message, as a reply in a thread if `thread_ts` is given (chat.postMessage)
+    fn post_reply(
+        &self,
+        channel: &str,
+        text: &str,
+        thread_ts: Option<&str>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Up to `limit` messages matching a query (search.messages)
+    fn search_messages(
+        &self,
+        query: &SearchQuery,
+        limit: usize,
+    ) -> impl Future<Output = Result<Vec<SlackSearchMatch>>> + Send;
+
+    /// Describe the request `post_reply` would send, without sending it.
+    /// The token is redacted and the form payload is shown as JSON.
+    fn describe_post_message(
+        &self,
+        channel: &str,
+        text: &str,
+        thread_ts: Option<&str>,
+    ) -> Result<String>;
+
+    /// Post a message to a channel
+    fn post_message(&self, channel: &str, text: &str) -> impl Future<Output = Result<()>> + Send {
+        self.post_reply(channel, text, None)
+    }
+
+    /// Resolve a channel reference against the listed channels,
+    /// falling back to conversations.info for IDs that are not listed.
+    fn resolve_channel(
+        &self,
+        channels: &SlackChannels,
+        reference: &ChannelRef,
+    ) -> impl Future<Output = Result<Option<SlackChannel>>> + Send {
+        let listed = channels.find(reference).cloned();
+        async move {
+            if listed.is_some() {
+                return Ok(listed);
+            }
+
+            match reference {
+                ChannelRef::Id(id) => self.get_channel_info(id).await.map(Some),
+                ChannelRef::Name(_) => Ok(None),
+            }
+        }
+    }
+
+    /// Post the same message to several channels concurrently.
+    /// At most `concurrency` requests are in flight at a time.
+    /// Results are returned in the order of `channels`.
+    fn post_message_to_channels(
+        &self,
+        channels: &[SlackChannel],
+        text: &str,
+        thread_ts: Option<&str>,
+        concurrency: usize,
+    ) -> impl Future<Output = Vec<(SlackChannel, Result<()>)>> + Send {
+        let (client, channels) = (self.clone(), channels.to_vec());
+        let (text, thread_ts) = (text.to_string(), thread_ts.map(str::to_string));
+        async move {
+            let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+            let mut tasks = JoinSet::new();
+
+            for (index, channel) in channels.iter().enumerate() {
+                let client = client.clone();
+                let semaphore = Arc::clone(&semaphore);
+                let (channel_id, text, thread_ts) =
+                    (channel.id.clone(), text.clone(), thread_ts.clone());
+                tasks.spawn(async move {
+                    let _permit = semaphore.acquire_owned().await;
+                    let res = client
+                        .post_reply(&channel_id, &text, thread_ts.as_deref())
+                        .await;
+                    (index, res)
+                });
+            }
+
+            let mut results: Vec<Option<Result<()>>> = channels.iter().map(|_| None).collect();
+            while let Some(joined) = tasks.join_next().await {
+                if let Ok((index, result)) = joined {
+                    results[index] = Some(result);
+                }
+            }
+
+            channels
+                .into_iter()
+                .zip(results)
+                .map(|(channel, result)| {
+                    (
+                        channel,
+                        result.unwrap_or_else(|| Err(anyhow!("Message was not posted"))),
+                    )
+                })
+                .collect()
+        }
+    }
+}
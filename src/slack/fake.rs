@@ -0,0 +1,500 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::{anyhow, Result};
+
+use super::{
+    HistoryRange, SearchQuery, SlackApi, SlackChannel, SlackChannels, SlackMessage, SlackMessages,
+    SlackSearchMatch, SlackUser, SlackUsers,
+};
+
+/// User ID of messages posted through the fake
+pub const FAKE_BOT_USER: &str = "U0000FAKE";
+
+/// Timestamp of the first message; later messages are one second apart
+const FIRST_TS: u64 = 1_700_000_000;
+/// Base URL shown by `describe_post_message` and in permalinks
+const FAKE_URL: &str = "https://fake.slack.com";
+
+/// Slack workspace kept in memory, for testing without a server.
+/// Clones share the workspace, so a post made through one clone shows up in all of them.
+#[derive(Clone, Default)]
+pub struct FakeSlack {
+    workspace: Arc<Mutex<Workspace>>,
+}
+
+#[derive(Default)]
+struct Workspace {
+    channels: Vec<SlackChannel>,
+    users: Vec<SlackUser>,
+    emoji: Vec<String>,
+    /// Messages and replies per channel ID, oldest first
+    messages: BTreeMap<String, Vec<SlackMessage>>,
+    /// Slack errors returned when posting to a channel ID
+    post_errors: BTreeMap<String, String>,
+    clock: u64,
+}
+
+impl Workspace {
+    fn next_ts(&mut self) -> String {
+        self.clock += 1;
+        format!("{}.000000", FIRST_TS + self.clock)
+    }
+
+    fn channel(&self, channel_id: &str) -> Result<&SlackChannel> {
+        self.channels
+            .iter()
+            .find(|channel| channel.id == channel_id)
+            .ok_or_else(|| anyhow!("channel_not_found"))
+    }
+
+    /*
+     * Messages of a channel that are not thread replies, oldest first.
+     */
+    fn top_level(&self, channel_id: &str, range: &HistoryRange) -> Result<Vec<SlackMessage>> {
+        self.channel(channel_id)?;
+        Ok(self
+            .messages
+            .get(channel_id)
+            .into_iter()
+            .flatten()
+            .filter(|message| {
+                message
+                    .thread_ts
+                    .as_ref()
+                    .is_none_or(|ts| *ts == message.ts)
+            })
+            .filter(|message| in_range(&message.ts, range))
+            .cloned()
+            .collect())
+    }
+}
+
+impl FakeSlack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a channel
+    pub fn with_channel(self, id: &str, name: &str) -> Self {
+        self.workspace().channels.push(SlackChannel {
+            id: id.to_string(),
+            name: name.to_string(),
+        });
+        self
+    }
+
+    /// Add a workspace member
+    pub fn with_user(self, id: &str, name: &str) -> Self {
+        self.workspace().users.push(SlackUser {
+            id: id.to_string(),
+            name: name.to_string(),
+            real_name: None,
+            profile: None,
+        });
+        self
+    }
+
+    /// Add a custom emoji
+    pub fn with_emoji(self, name: &str) -> Self {
+        self.workspace().emoji.push(name.to_string());
+        self
+    }
+
+    /// Add a message to a channel's history
+    pub fn with_message(self, channel_id: &str, user_id: &str, text: &str) -> Self {
+        let mut workspace = self.workspace();
+        let ts = workspace.next_ts();
+        workspace
+            .messages
+            .entry(channel_id.to_string())
+            .or_default()
+            .push(SlackMessage {
+                text: text.to_string(),
+                user: Some(user_id.to_string()),
+                ts,
+                thread_ts: None,
+                reply_count: None,
+            });
+        drop(workspace);
+        self
+    }
+
+    /// Make posts to a channel fail with a Slack error such as `is_archived`
+    pub fn with_post_error(self, channel_id: &str, error: &str) -> Self {
+        self.workspace()
+            .post_errors
+            .insert(channel_id.to_string(), error.to_string());
+        self
+    }
+
+    /// Messages and replies in a channel, oldest first
+    pub fn messages(&self, channel_id: &str) -> Vec<SlackMessage> {
+        self.workspace()
+            .messages
+            .get(channel_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Post a message like chat.postMessage and return its `ts`
+    pub fn post(&self, channel: &str, text: &str, thread_ts: Option<&str>) -> Result<String> {
+        let mut workspace = self.workspace();
+        workspace.channel(channel)?;
+        if let Some(error) = workspace.post_errors.get(channel) {
+            return Err(anyhow!("{}", error));
+        }
+
+        let ts = workspace.next_ts();
+        let messages = workspace.messages.entry(channel.to_string()).or_default();
+        if let Some(thread_ts) = thread_ts {
+            let parent = messages
+                .iter_mut()
+                .find(|message| message.ts == thread_ts)
+                .ok_or_else(|| anyhow!("thread_not_found"))?;
+            parent.thread_ts = Some(thread_ts.to_string());
+            parent.reply_count = Some(parent.reply_count.unwrap_or_default() + 1);
+        }
+        messages.push(SlackMessage {
+            text: text.to_string(),
+            user: Some(FAKE_BOT_USER.to_string()),
+            ts: ts.clone(),
+            thread_ts: thread_ts.map(str::to_string),
+            reply_count: None,
+        });
+        Ok(ts)
+    }
+
+    fn workspace(&self) -> MutexGuard<'_, Workspace> {
+        // Updates cannot panic halfway, so a poisoned lock still guards consistent data
+        self.workspace
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl SlackApi for FakeSlack {
+    async fn get_channels(&self) -> Result<SlackChannels> {
+        Ok(self.workspace().channels.clone().into())
+    }
+
+    async fn get_channel_info(&self, channel_id: &str) -> Result<SlackChannel> {
+        self.workspace().channel(channel_id).cloned()
+    }
+
+    async fn get_messages(
+        &self,
+        channel_id: &str,
+        limit: usize,
+        range: &HistoryRange,
+    ) -> Result<SlackMessages> {
+        let mut messages = self.workspace().top_level(channel_id, range)?;
+        messages.reverse();
+        messages.truncate(limit);
+        Ok(messages.into())
+    }
+
+    async fn get_history(&self, channel_id: &str, range: &HistoryRange) -> Result<SlackMessages> {
+        let mut messages = self.workspace().top_level(channel_id, range)?;
+        messages.reverse();
+        Ok(messages.into())
+    }
+
+    async fn get_replies(&self, channel_id: &str, thread_ts: &str) -> Result<SlackMessages> {
+        let workspace = self.workspace();
+        workspace.channel(channel_id)?;
+        let thread: Vec<SlackMessage> = workspace
+            .messages
+            .get(channel_id)
+            .into_iter()
+            .flatten()
+            .filter(|message| {
+                message.ts == thread_ts || message.thread_ts.as_deref() == Some(thread_ts)
+            })
+            .cloned()
+            .collect();
+
+        if thread.is_empty() {
+            return Err(anyhow!("thread_not_found"));
+        }
+        Ok(thread.into())
+    }
+
+    async fn get_users(&self) -> Result<SlackUsers> {
+        Ok(self.workspace().users.clone().into())
+    }
+
+    async fn get_emoji(&self) -> Result<Vec<String>> {
+        Ok(self.workspace().emoji.clone())
+    }
+
+    /*
+     * Match words of the query in message text, case-insensitively, newest first.
+     * `in:` takes a channel name, `from:` a user ID or name and `after:` a date;
+     * as in Slack, `after:` matches messages from the following day on.
+     */
+    async fn search_messages(
+        &self,
+        query: &SearchQuery,
+        limit: usize,
+    ) -> Result<Vec<SlackSearchMatch>> {
+        let after = match &query.after {
+            Some(date) => {
+                let date = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|_| anyhow!("invalid_date"))?;
+                date.succ_opt()
+                    .and_then(|day| day.and_hms_opt(0, 0, 0))
+                    .map(|start| start.and_utc().timestamp() as f64)
+            }
+            None => None,
+        };
+        let words: Vec<String> = query
+            .query
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect();
+
+        let workspace = self.workspace();
+        let name_of = |user_id: &str| {
+            workspace
+                .users
+                .iter()
+                .find(|user| user.id == user_id)
+                .map(|user| user.name.clone())
+        };
+        let mut matches: Vec<SlackSearchMatch> = workspace
+            .channels
+            .iter()
+            .filter(|channel| {
+                query
+                    .in_channel
+                    .as_deref()
+                    .is_none_or(|name| name.trim_start_matches('#') == channel.name)
+            })
+            .flat_map(|channel| {
+                workspace
+                    .messages
+                    .get(&channel.id)
+                    .into_iter()
+                    .flatten()
+                    .map(move |message| (channel, message))
+            })
+            .filter(|(_, message)| {
+                let text = message.text.to_lowercase();
+                words.iter().all(|word| text.contains(word.as_str()))
+            })
+            .filter(|(_, message)| {
+                query.from.as_deref().is_none_or(|from| {
+                    let from = from.trim_start_matches('@');
+                    message
+                        .user
+                        .as_deref()
+                        .is_some_and(|user| user == from || name_of(user).as_deref() == Some(from))
+                })
+            })
+            .filter(|(_, message)| {
+                after.is_none_or(|after| message.ts.parse::<f64>().unwrap_or_default() >= after)
+            })
+            .map(|(channel, message)| SlackSearchMatch {
+                channel: channel.clone(),
+                user: message.user.clone(),
+                username: message.user.as_deref().and_then(name_of),
+                ts: message.ts.clone(),
+                text: message.text.clone(),
+                permalink: format!(
+                    "{}/archives/{}/p{}",
+                    FAKE_URL,
+                    channel.id,
+                    message.ts.replace('.', "")
+                ),
+            })
+            .collect();
+
+        matches.sort_by(|a, b| b.ts.cmp(&a.ts));
+        matches.truncate(limit);
+        Ok(matches)
+    }
+
+    fn describe_post_message(
+        &self,
+        channel: &str,
+        text: &str,
+        thread_ts: Option<&str>,
+    ) -> Result<String> {
+        let mut payload = serde_json::Map::new();
+        payload.insert("channel".to_string(), channel.into());
+        payload.insert("text".to_string(), text.into());
+        if let Some(thread_ts) = thread_ts {
+            payload.insert("thread_ts".to_string(), thread_ts.into());
+        }
+        Ok(format!(
+            "POST {}/api/chat.postMessage\n\n{}",
+            FAKE_URL,
+            serde_json::to_string_pretty(&payload)?
+        ))
+    }
+
+    async fn post_reply(&self, channel: &str, text: &str, thread_ts: Option<&str>) -> Result<()> {
+        self.post(channel, text, thread_ts).map(|_| ())
+    }
+}
+
+/*
+ * Check a message timestamp against a history range the way Slack does:
+ * the bounds are exclusive unless the range is inclusive.
+ */
+fn in_range(ts: &str, range: &HistoryRange) -> bool {
+    let ts = ts.parse::<f64>().unwrap_or_default();
+    let bound = |bound: &Option<String>| bound.as_ref().and_then(|b| b.parse::<f64>().ok());
+    let after_oldest =
+        bound(&range.oldest).is_none_or(|oldest| ts > oldest || (range.inclusive && ts == oldest));
+    let before_latest =
+        bound(&range.latest).is_none_or(|latest| ts < latest || (range.inclusive && ts == latest));
+    after_oldest && before_latest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slack::{ChannelRef, MAX_CONCURRENT_POSTS};
+
+    fn workspace() -> FakeSlack {
+        FakeSlack::new()
+            .with_channel("C001", "general")
+            .with_channel("C002", "archived")
+            .with_user("U001", "alice")
+            .with_message("C001", "U001", "first")
+            .with_message("C001", "U001", "second")
+            .with_message("C001", "U001", "third")
+            .with_post_error("C002", "is_archived")
+    }
+
+    fn texts(messages: &SlackMessages) -> Vec<&str> {
+        messages.messages.iter().map(|m| m.text.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn it_reads_history() {
+        let slack = workspace();
+        let newest = slack
+            .get_messages("C001", 2, &HistoryRange::default())
+            .await
+            .unwrap();
+        assert_eq!(texts(&newest), vec!["third", "second"]);
+
+        let range = HistoryRange {
+            oldest: Some("1700000001.000000".to_string()),
+            latest: None,
+            inclusive: false,
+        };
+        let history = slack.get_history("C001", &range).await.unwrap();
+        assert_eq!(texts(&history), vec!["third", "second"]);
+
+        assert!(slack
+            .get_messages("C404", 10, &HistoryRange::default())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn it_posts_messages_and_replies() {
+        let slack = workspace();
+        let parent = slack.messages("C001")[0].ts.clone();
+
+        slack.post_message("C001", "hello").await.unwrap();
+        slack
+            .clone()
+            .post_reply("C001", "in thread", Some(&parent))
+            .await
+            .unwrap();
+
+        let newest = slack
+            .get_messages("C001", 1, &HistoryRange::default())
+            .await
+            .unwrap();
+        assert_eq!(texts(&newest), vec!["hello"]);
+        assert_eq!(newest.messages[0].user.as_deref(), Some(FAKE_BOT_USER));
+
+        let thread = slack.get_replies("C001", &parent).await.unwrap();
+        assert_eq!(texts(&thread), vec!["first", "in thread"]);
+        assert!(thread.messages[0].has_replies());
+    }
+
+    #[tokio::test]
+    async fn it_posts_to_channels_and_resolves_them() {
+        let slack = workspace();
+        let channels = slack.get_channels().await.unwrap();
+        let results = slack
+            .post_message_to_channels(&channels.channels, "hi", None, MAX_CONCURRENT_POSTS)
+            .await;
+        assert!(results[0].1.is_ok());
+        match &results[1].1 {
+            Err(err) => assert_eq!(err.to_string(), "is_archived"),
+            Ok(()) => panic!("expected is_archived error"),
+        }
+
+        let resolved = slack
+            .resolve_channel(&Vec::new().into(), &ChannelRef::Id("C001".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(resolved.map(|c| c.name), Some("general".to_string()));
+    }
+
+    #[tokio::test]
+    async fn it_searches_messages() {
+        let slack =
+            workspace()
+                .with_user("U002", "bob")
+                .with_message("C001", "U002", "Third time lucky");
+        let texts = |matches: Vec<SlackSearchMatch>| -> Vec<String> {
+            matches.into_iter().map(|found| found.text).collect()
+        };
+
+        let query = SearchQuery {
+            query: "THIRD".to_string(),
+            ..SearchQuery::default()
+        };
+        let found = slack.search_messages(&query, 10).await.unwrap();
+        assert_eq!(found[0].username.as_deref(), Some("bob"));
+        assert_eq!(
+            found[0].permalink,
+            "https://fake.slack.com/archives/C001/p1700000004000000"
+        );
+        assert_eq!(texts(found), vec!["Third time lucky", "third"]);
+
+        let query = SearchQuery {
+            query: "third".to_string(),
+            from: Some("@alice".to_string()),
+            in_channel: Some("#general".to_string()),
+            ..SearchQuery::default()
+        };
+        assert_eq!(
+            texts(slack.search_messages(&query, 10).await.unwrap()),
+            vec!["third"]
+        );
+
+        let query = SearchQuery {
+            after: Some("2023-11-14".to_string()),
+            ..SearchQuery::default()
+        };
+        assert!(slack.search_messages(&query, 10).await.unwrap().is_empty());
+        let query = SearchQuery {
+            after: Some("2023-11-13".to_string()),
+            ..SearchQuery::default()
+        };
+        assert_eq!(slack.search_messages(&query, 2).await.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn it_describes_posts() {
+        let slack = workspace();
+        let description = slack
+            .describe_post_message("C001", "hi", Some("1700000001.000000"))
+            .unwrap();
+        assert_eq!(
+            description,
+            "POST https://fake.slack.com/api/chat.postMessage\n\n{\n  \"channel\": \"C001\",\n  \"text\": \"hi\",\n  \"thread_ts\": \"1700000001.000000\"\n}"
+        );
+        assert_eq!(slack.messages("C001").len(), 3);
+    }
+}
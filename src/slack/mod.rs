@@ -1,16 +1,52 @@
+use std::collections::BTreeMap;
+use std::fs;
+
 use anyhow::{anyhow, Result};
-use reqwest::Client;
-use serde::Deserialize;
+use reqwest::header::AUTHORIZATION;
+use reqwest::{Certificate, Client, Proxy, Request};
+use serde::{Deserialize, Serialize};
 use url::Url;
 
 use crate::config::Config;
 
+mod api;
+mod fake;
+mod text;
+
+pub use api::SlackApi;
+pub use fake::{FakeSlack, FAKE_BOT_USER};
+pub use text::{broadcast_mentions, split_message, MAX_MESSAGE_LENGTH};
+
+/// Maximum number of messages posted at the same time when broadcasting
+pub const MAX_CONCURRENT_POSTS: usize = 4;
+
 #[derive(Deserialize)]
 pub struct SlackResponse {
     ok: bool,
     pub error: Option<String>,
     pub channels: Option<Vec<SlackChannel>>,
+    pub channel: Option<SlackChannel>,
     pub messages: Option<Vec<SlackMessage>>,
+    pub members: Option<Vec<SlackUser>>,
+    pub emoji: Option<BTreeMap<String, String>>,
+    pub response_metadata: Option<SlackResponseMetadata>,
+}
+
+#[derive(Deserialize)]
+pub struct SlackResponseMetadata {
+    pub next_cursor: Option<String>,
+}
+
+impl SlackResponse {
+    /*
+     * Cursor for the next page, if there is one.
+     */
+    fn next_cursor(&self) -> Option<String> {
+        self.response_metadata
+            .as_ref()
+            .and_then(|metadata| metadata.next_cursor.clone())
+            .filter(|cursor| !cursor.is_empty())
+    }
 }
 
 pub struct SlackChannels {
@@ -23,29 +59,220 @@ impl From<Vec<SlackChannel>> for SlackChannels {
     }
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
 pub struct SlackChannel {
     pub id: String,
     pub name: String,
 }
 
-#[derive(Clone, Deserialize)]
+/// A channel given on the command line, either by name or by ID
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChannelRef {
+    Name(String),
+    Id(String),
+}
+
+impl ChannelRef {
+    /// Parse `general`, `#general`, `C0123456789` or a permalink such as
+    /// `https://example.slack.com/archives/C0123456789/p1700000000000000`.
+    pub fn parse(input: &str) -> Self {
+        let input = input.trim();
+
+        if input.starts_with("https://") || input.starts_with("http://") {
+            if let Some(id) = Url::parse(input).ok().and_then(|url| {
+                let mut segments = url.path_segments()?;
+                segments.find(|segment| *segment == "archives")?;
+                segments.next().map(str::to_string)
+            }) {
+                return ChannelRef::Id(id);
+            }
+        }
+
+        let input = input.strip_prefix('#').unwrap_or(input);
+        if Self::is_channel_id(input) {
+            ChannelRef::Id(input.to_string())
+        } else {
+            ChannelRef::Name(input.to_string())
+        }
+    }
+
+    /*
+     * Channel IDs start with C (public), G (private) or D (direct message)
+     * and, unlike channel names, only contain upper case letters and digits.
+     */
+    fn is_channel_id(input: &str) -> bool {
+        input.len() >= 9
+            && input.starts_with(['C', 'G', 'D'])
+            && input
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct SlackMessage {
     pub text: String,
     pub user: Option<String>,
     pub ts: String,
+    pub thread_ts: Option<String>,
+    pub reply_count: Option<usize>,
+}
+
+impl SlackMessage {
+    /// Check if the message starts a thread with replies
+    pub fn has_replies(&self) -> bool {
+        self.reply_count.unwrap_or_default() > 0
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct SlackUser {
+    pub id: String,
+    pub name: String,
+    pub real_name: Option<String>,
+    pub profile: Option<SlackUserProfile>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct SlackUserProfile {
+    pub display_name: Option<String>,
+}
+
+impl SlackUser {
+    /// Name shown in Slack: display name, then real name, then user name
+    pub fn display_name(&self) -> &str {
+        self.profile
+            .as_ref()
+            .and_then(|profile| profile.display_name.as_deref())
+            .filter(|name| !name.is_empty())
+            .or(self.real_name.as_deref().filter(|name| !name.is_empty()))
+            .unwrap_or(&self.name)
+    }
+}
+
+pub struct SlackUsers {
+    pub users: Vec<SlackUser>,
+}
+
+impl From<Vec<SlackUser>> for SlackUsers {
+    fn from(users: Vec<SlackUser>) -> Self {
+        Self { users }
+    }
+}
+
+impl SlackUsers {
+    pub fn find_by_id(&self, id: &str) -> Option<&SlackUser> {
+        self.users.iter().find(|user| user.id == id)
+    }
+
+    /// Display name for a user ID, or the ID itself when the user is unknown
+    pub fn name_of<'a>(&'a self, id: &'a str) -> &'a str {
+        self.find_by_id(id)
+            .map(SlackUser::display_name)
+            .unwrap_or(id)
+    }
+}
+
+/// Time range for conversations.history, as Slack timestamps
+#[derive(Clone, Default)]
+pub struct HistoryRange {
+    pub oldest: Option<String>,
+    pub latest: Option<String>,
+    /// Include messages exactly at `oldest` or `latest`
+    pub inclusive: bool,
+}
+
+impl HistoryRange {
+    /*
+     * Query parameters for conversations.history.
+     */
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+        if let Some(oldest) = &self.oldest {
+            params.push(("oldest", oldest.clone()));
+        }
+        if let Some(latest) = &self.latest {
+            params.push(("latest", latest.clone()));
+        }
+        if self.inclusive {
+            params.push(("inclusive", "true".to_string()));
+        }
+        params
+    }
 }
 
+/// Number of items requested per page when following cursors
+const PAGE_SIZE: usize = 200;
+
 pub struct SlackMessages {
     pub messages: Vec<SlackMessage>,
 }
 
+#[derive(Deserialize)]
+pub struct SlackSearchResponse {
+    ok: bool,
+    pub error: Option<String>,
+    pub messages: Option<SlackSearchMessages>,
+}
+
+#[derive(Deserialize)]
+pub struct SlackSearchMessages {
+    pub matches: Vec<SlackSearchMatch>,
+    pub paging: SlackPaging,
+}
+
+#[derive(Deserialize)]
+pub struct SlackPaging {
+    pub page: usize,
+    pub pages: usize,
+}
+
+/// A message found by search.messages
+#[derive(Clone, Deserialize, Serialize)]
+pub struct SlackSearchMatch {
+    pub channel: SlackChannel,
+    pub user: Option<String>,
+    pub username: Option<String>,
+    pub ts: String,
+    pub text: String,
+    pub permalink: String,
+}
+
+/// Query for search.messages with optional `in:`, `from:` and `after:` modifiers
+#[derive(Default)]
+pub struct SearchQuery {
+    pub query: String,
+    pub in_channel: Option<String>,
+    pub from: Option<String>,
+    pub after: Option<String>,
+}
+
+impl SearchQuery {
+    pub fn to_query_string(&self) -> String {
+        let mut terms = vec![self.query.trim().to_string()];
+        if let Some(channel) = &self.in_channel {
+            terms.push(format!("in:#{}", channel.trim_start_matches('#')));
+        }
+        if let Some(user) = &self.from {
+            terms.push(format!("from:@{}", user.trim_start_matches('@')));
+        }
+        if let Some(date) = &self.after {
+            terms.push(format!("after:{}", date));
+        }
+
+        terms.retain(|term| !term.is_empty());
+        terms.join(" ")
+    }
+}
+
 impl From<Vec<SlackMessage>> for SlackMessages {
     fn from(messages: Vec<SlackMessage>) -> Self {
         Self { messages }
     }
 }
 
+#[derive(Clone)]
 pub struct SlackClient {
     pub client: Client,
     pub base_url: String,
@@ -53,20 +280,80 @@ pub struct SlackClient {
 }
 
 impl SlackClient {
-    pub fn new(config: &Config, base_url: &str) -> Self {
-        Self {
-            client: Client::new(),
-            base_url: base_url.to_owned(),
+    /*
+     * Build an HTTP client with the timeouts, proxy, certificates and
+     * user-agent from the config. `base_url` is usually `config.base_url()`.
+     */
+    pub fn new(config: &Config, base_url: &str) -> Result<Self> {
+        let mut builder = Client::builder()
+            .timeout(config.timeout())
+            .connect_timeout(config.connect_timeout())
+            .user_agent(config.user_agent());
+
+        if let Some(proxy) = config.proxy() {
+            let proxy = Proxy::all(proxy).map_err(|e| anyhow!("Invalid proxy {}: {}", proxy, e))?;
+            builder = builder.proxy(proxy);
+        }
+        if let Some(path) = config.ca_bundle() {
+            let pem = fs::read(path).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+            let certs = Certificate::from_pem_bundle(&pem)
+                .map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+            builder = builder.tls_certs_merge(certs);
+        }
+
+        Ok(Self {
+            client: builder.build()?,
+            base_url: base_url.trim_end_matches('/').to_owned(),
             bearer_token: config.token().to_string(),
+        })
+    }
+
+    /*
+     * Build the chat.postMessage request for a message.
+     */
+    fn post_message_request(
+        &self,
+        channel: &str,
+        text: &str,
+        thread_ts: Option<&str>,
+    ) -> Result<Request> {
+        let mut body = vec![("channel", channel), ("text", text)];
+        if let Some(thread_ts) = thread_ts {
+            body.push(("thread_ts", thread_ts));
         }
+        let url = Url::parse(&format!("{}{}", self.base_url, "/api/chat.postMessage"))
+            .map_err(|e| anyhow!("Invalid URL: {}", e))?;
+
+        Ok(self
+            .client
+            .post(url)
+            .bearer_auth(&self.bearer_token)
+            .form(&body)
+            .build()?)
     }
 
     /*
-     * Get slack channels.
+     * Get a single page of a cursor-paginated endpoint.
      */
-    pub async fn get_channels(&self) -> Result<SlackChannels> {
-        let url = Url::parse(&format!("{}{}", self.base_url, "/api/conversations.list"))
+    async fn get_page(
+        &self,
+        endpoint: &str,
+        params: &[(&str, String)],
+        page_size: usize,
+        cursor: Option<&str>,
+    ) -> Result<SlackResponse> {
+        let mut url = Url::parse(&format!("{}{}", self.base_url, endpoint))
             .map_err(|e| anyhow!("Invalid URL: {}", e))?;
+        {
+            let mut query = url.query_pairs_mut();
+            for (key, value) in params {
+                query.append_pair(key, value);
+            }
+            query.append_pair("limit", &page_size.to_string());
+            if let Some(cursor) = cursor {
+                query.append_pair("cursor", cursor);
+            }
+        }
 
         let res: SlackResponse = self
             .client
@@ -78,12 +365,7 @@ impl SlackClient {
             .await?;
 
         if res.ok {
-            match res.channels {
-                Some(channels) => Ok(SlackChannels::from(channels)),
-                None => {
-                    return Err(anyhow!("No channels found"));
-                }
-            }
+            Ok(res)
         } else {
             Err(anyhow!(
                 "{}",
@@ -93,23 +375,138 @@ impl SlackClient {
     }
 
     /*
-     * Post slack message.
+     * Get every page of a cursor-paginated endpoint.
      */
-    pub async fn post_message(&self, channel: &str, text: &str) -> Result<SlackResponse> {
-        let body = vec![("channel", channel), ("text", text)];
-        let url = Url::parse(&format!("{}{}", self.base_url, "/api/chat.postMessage"))
+    async fn get_all_pages(
+        &self,
+        endpoint: &str,
+        params: &[(&str, String)],
+    ) -> Result<Vec<SlackResponse>> {
+        let mut pages = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let res = self
+                .get_page(endpoint, params, PAGE_SIZE, cursor.as_deref())
+                .await?;
+
+            cursor = res.next_cursor();
+            pages.push(res);
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        Ok(pages)
+    }
+}
+
+impl SlackApi for SlackClient {
+    /*
+     * Describe the chat.postMessage request with the token redacted and the form payload as JSON.
+     */
+    fn describe_post_message(
+        &self,
+        channel: &str,
+        text: &str,
+        thread_ts: Option<&str>,
+    ) -> Result<String> {
+        let request = self.post_message_request(channel, text, thread_ts)?;
+
+        let mut lines = vec![format!("{} {}", request.method(), request.url())];
+        for (name, value) in request.headers() {
+            let value = if name == AUTHORIZATION {
+                "Bearer [REDACTED]"
+            } else {
+                value.to_str().unwrap_or_default()
+            };
+            lines.push(format!("{}: {}", name, value));
+        }
+
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .unwrap_or_default();
+        let payload: serde_json::Map<String, serde_json::Value> = url::form_urlencoded::parse(body)
+            .map(|(key, value)| (key.into_owned(), value.into_owned().into()))
+            .collect();
+        lines.push(String::new());
+        lines.push(serde_json::to_string_pretty(&payload)?);
+
+        Ok(lines.join("\n"))
+    }
+
+    /*
+     * Search messages (search.messages), following pages until `limit` matches are found.
+     */
+    async fn search_messages(
+        &self,
+        query: &SearchQuery,
+        limit: usize,
+    ) -> Result<Vec<SlackSearchMatch>> {
+        let mut matches = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let mut url = Url::parse(&format!("{}{}", self.base_url, "/api/search.messages"))
+                .map_err(|e| anyhow!("Invalid URL: {}", e))?;
+            url.query_pairs_mut()
+                .append_pair("query", &query.to_query_string())
+                .append_pair("count", &limit.min(100).to_string())
+                .append_pair("page", &page.to_string());
+
+            let res: SlackSearchResponse = self
+                .client
+                .get(url)
+                .bearer_auth(&self.bearer_token)
+                .send()
+                .await?
+                .json()
+                .await?;
+
+            if !res.ok {
+                return Err(anyhow!(
+                    "{}",
+                    res.error.unwrap_or_else(|| "Unknown error".to_string())
+                ));
+            }
+
+            let Some(messages) = res.messages else {
+                break;
+            };
+            matches.extend(messages.matches);
+
+            if matches.len() >= limit || messages.paging.page >= messages.paging.pages {
+                break;
+            }
+            page = messages.paging.page + 1;
+        }
+
+        matches.truncate(limit);
+        Ok(matches)
+    }
+
+    /*
+     * Get slack channels.
+     */
+    async fn get_channels(&self) -> Result<SlackChannels> {
+        let url = Url::parse(&format!("{}{}", self.base_url, "/api/conversations.list"))
             .map_err(|e| anyhow!("Invalid URL: {}", e))?;
 
-        let client = self
+        let res: SlackResponse = self
             .client
-            .post(url)
+            .get(url)
             .bearer_auth(&self.bearer_token)
-            .form(&body);
-
-        let res: SlackResponse = client.send().await?.json().await?;
+            .send()
+            .await?
+            .json()
+            .await?;
 
         if res.ok {
-            Ok(res)
+            match res.channels {
+                Some(channels) => Ok(SlackChannels::from(channels)),
+                None => Err(anyhow!("No channels found")),
+            }
         } else {
             Err(anyhow!(
                 "{}",
@@ -119,12 +516,12 @@ impl SlackClient {
     }
 
     /*
-     * Get channel messages (conversations.history).
+     * Get a single slack channel (conversations.info).
      */
-    pub async fn get_messages(&self, channel_id: &str, limit: usize) -> Result<SlackMessages> {
+    async fn get_channel_info(&self, channel_id: &str) -> Result<SlackChannel> {
         let url = Url::parse(&format!(
-            "{}{}?channel={}&limit={}",
-            self.base_url, "/api/conversations.history", channel_id, limit
+            "{}{}?channel={}",
+            self.base_url, "/api/conversations.info", channel_id
         ))
         .map_err(|e| anyhow!("Invalid URL: {}", e))?;
 
@@ -138,10 +535,149 @@ impl SlackClient {
             .await?;
 
         if res.ok {
+            res.channel
+                .ok_or_else(|| anyhow!("Channel '{}' not found", channel_id))
+        } else {
+            Err(anyhow!(
+                "{}",
+                res.error.unwrap_or_else(|| "Unknown error".to_string())
+            ))
+        }
+    }
+
+    /*
+     * Get up to `limit` channel messages in a range (conversations.history), newest first.
+     * Follows cursors when the range spans more than one page.
+     */
+    async fn get_messages(
+        &self,
+        channel_id: &str,
+        limit: usize,
+        range: &HistoryRange,
+    ) -> Result<SlackMessages> {
+        let mut params = vec![("channel", channel_id.to_string())];
+        params.extend(range.params());
+
+        let mut messages = Vec::new();
+        let mut cursor: Option<String> = None;
+        while messages.len() < limit {
+            let page_size = (limit - messages.len()).min(PAGE_SIZE);
+            let res = self
+                .get_page(
+                    "/api/conversations.history",
+                    &params,
+                    page_size,
+                    cursor.as_deref(),
+                )
+                .await?;
+
+            cursor = res.next_cursor();
             match res.messages {
-                Some(messages) => Ok(SlackMessages::from(messages)),
-                None => Err(anyhow!("No messages found")),
+                Some(page) => messages.extend(page),
+                None => return Err(anyhow!("No messages found")),
+            }
+            if cursor.is_none() {
+                break;
             }
+        }
+
+        messages.truncate(limit);
+        Ok(SlackMessages::from(messages))
+    }
+
+    /*
+     * Get all channel messages in a time range (conversations.history), newest first.
+     */
+    async fn get_history(&self, channel_id: &str, range: &HistoryRange) -> Result<SlackMessages> {
+        let mut params = vec![("channel", channel_id.to_string())];
+        params.extend(range.params());
+
+        let pages = self
+            .get_all_pages("/api/conversations.history", &params)
+            .await?;
+        Ok(SlackMessages::from(
+            pages
+                .into_iter()
+                .flat_map(|page| page.messages.unwrap_or_default())
+                .collect::<Vec<_>>(),
+        ))
+    }
+
+    /*
+     * Get all messages of a thread (conversations.replies), parent first.
+     */
+    async fn get_replies(&self, channel_id: &str, thread_ts: &str) -> Result<SlackMessages> {
+        let params = [
+            ("channel", channel_id.to_string()),
+            ("ts", thread_ts.to_string()),
+        ];
+
+        let pages = self
+            .get_all_pages("/api/conversations.replies", &params)
+            .await?;
+        Ok(SlackMessages::from(
+            pages
+                .into_iter()
+                .flat_map(|page| page.messages.unwrap_or_default())
+                .collect::<Vec<_>>(),
+        ))
+    }
+
+    /*
+     * Get workspace users (users.list).
+     */
+    async fn get_users(&self) -> Result<SlackUsers> {
+        let pages = self.get_all_pages("/api/users.list", &[]).await?;
+        Ok(SlackUsers::from(
+            pages
+                .into_iter()
+                .flat_map(|page| page.members.unwrap_or_default())
+                .collect::<Vec<_>>(),
+        ))
+    }
+
+    /*
+     * Get the names of the workspace's custom emoji (emoji.list), sorted.
+     */
+    async fn get_emoji(&self) -> Result<Vec<String>> {
+        let url = Url::parse(&format!("{}{}", self.base_url, "/api/emoji.list"))
+            .map_err(|e| anyhow!("Invalid URL: {}", e))?;
+
+        let res: SlackResponse = self
+            .client
+            .get(url)
+            .bearer_auth(&self.bearer_token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        if res.ok {
+            Ok(res.emoji.unwrap_or_default().into_keys().collect())
+        } else {
+            Err(anyhow!(
+                "{}",
+                res.error.unwrap_or_else(|| "Unknown error".to_string())
+            ))
+        }
+    }
+
+    /*
+     * Post slack message. Network and server errors are returned as
+     * `reqwest::Error`, see `is_transient`.
+     */
+    async fn post_reply(&self, channel: &str, text: &str, thread_ts: Option<&str>) -> Result<()> {
+        let request = self.post_message_request(channel, text, thread_ts)?;
+        let response = self.client.execute(request).await?;
+        let response = if response.status().is_server_error() {
+            response.error_for_status()?
+        } else {
+            response
+        };
+        let res: SlackResponse = response.json().await?;
+
+        if res.ok {
+            Ok(())
         } else {
             Err(anyhow!(
                 "{}",
@@ -151,11 +687,29 @@ impl SlackClient {
     }
 }
 
+/// Check if a failed post may succeed when retried later:
+/// the network was unreachable, the request timed out or Slack returned a server error.
+pub fn is_transient(err: &anyhow::Error) -> bool {
+    match err.downcast_ref::<reqwest::Error>() {
+        Some(err) => {
+            err.is_connect()
+                || err.is_timeout()
+                || err.is_request()
+                || err.status().is_some_and(|status| status.is_server_error())
+        }
+        None => false,
+    }
+}
+
 impl SlackChannels {
     pub fn len(&self) -> usize {
         self.channels.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+
     pub fn channel_names(&self) -> Vec<String> {
         self.channels
             .iter()
@@ -173,11 +727,23 @@ impl SlackChannels {
     pub fn find_by_name(&self, name: &str) -> Option<&SlackChannel> {
         self.channels.iter().find(|c| c.name == name)
     }
+
+    pub fn find_by_id(&self, id: &str) -> Option<&SlackChannel> {
+        self.channels.iter().find(|c| c.id == id)
+    }
+
+    pub fn find(&self, reference: &ChannelRef) -> Option<&SlackChannel> {
+        match reference {
+            ChannelRef::Name(name) => self.find_by_name(name),
+            ChannelRef::Id(id) => self.find_by_id(id),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::SLACK_URL;
     use serial_test::serial;
 
     #[test]
@@ -185,10 +751,56 @@ mod tests {
     fn it_create_slack_client() {
         std::env::set_var("RSLACK_TOKEN", "test-token");
         let config = Config::new(None).unwrap();
-        let slack_client = SlackClient::new(&config, "https://example.com");
+        let slack_client = SlackClient::new(&config, "https://example.com").unwrap();
         assert_eq!(slack_client.bearer_token, config.token());
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn it_send_configured_user_agent() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/conversations.list")
+            .match_header("user-agent", "rslack-corp/1.0")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ok": true, "channels": []}"#)
+            .create_async()
+            .await;
+
+        std::env::set_var("RSLACK_TOKEN", "test-token");
+        std::env::set_var("RSLACK_USER_AGENT", "rslack-corp/1.0");
+        let config = Config::new(None).unwrap();
+        std::env::remove_var("RSLACK_USER_AGENT");
+        // A trailing slash in the base URL is ignored
+        let slack_client = SlackClient::new(&config, &format!("{}/", server.url())).unwrap();
+        slack_client.get_channels().await.unwrap();
+        mock.assert_async().await;
+    }
+
+    #[test]
+    #[serial]
+    fn it_create_slack_client_with_proxy_and_ca_bundle() {
+        std::env::set_var("RSLACK_TOKEN", "test-token");
+        std::env::set_var("RSLACK_PROXY", "http://proxy.example.com:8080");
+        std::env::set_var("RSLACK_CA_BUNDLE", "tests/fixtures/tls/ca.pem");
+        let config = Config::new(None).unwrap();
+        assert!(SlackClient::new(&config, SLACK_URL).is_ok());
+
+        std::env::set_var("RSLACK_CA_BUNDLE", "tests/fixtures/tls/missing.pem");
+        let config = Config::new(None).unwrap();
+        let err = SlackClient::new(&config, SLACK_URL).err().unwrap();
+        assert!(err
+            .to_string()
+            .starts_with("tests/fixtures/tls/missing.pem"));
+
+        std::env::remove_var("RSLACK_CA_BUNDLE");
+        std::env::set_var("RSLACK_PROXY", "not a proxy");
+        let config = Config::new(None).unwrap();
+        std::env::remove_var("RSLACK_PROXY");
+        assert!(SlackClient::new(&config, SLACK_URL).is_err());
+    }
+
     #[tokio::test]
     #[serial]
     async fn it_get_channels() {
@@ -203,7 +815,7 @@ mod tests {
 
         std::env::set_var("RSLACK_TOKEN", "test-token");
         let config = Config::new(None).unwrap();
-        let slack_client = SlackClient::new(&config, &server.url());
+        let slack_client = SlackClient::new(&config, &server.url()).unwrap();
         let channels = slack_client.get_channels().await.unwrap();
         assert_eq!(channels.len(), 2);
     }
@@ -290,12 +902,433 @@ mod tests {
 
         std::env::set_var("RSLACK_TOKEN", "test-token");
         let config = Config::new(None).unwrap();
-        let slack_client = SlackClient::new(&config, &server.url());
-        let messages = slack_client.get_messages("C0123456789", 10).await.unwrap();
+        let slack_client = SlackClient::new(&config, &server.url()).unwrap();
+        let messages = slack_client
+            .get_messages("C0123456789", 10, &HistoryRange::default())
+            .await
+            .unwrap();
         assert_eq!(messages.messages.len(), 3);
         assert_eq!(
             messages.messages[0].text,
             "Hello, this is the latest message!"
         );
     }
+
+    #[test]
+    #[serial]
+    fn it_describe_post_message() {
+        std::env::set_var("RSLACK_TOKEN", "test-token");
+        let config = Config::new(None).unwrap();
+        let slack_client = SlackClient::new(&config, "https://example.com").unwrap();
+        let description = slack_client
+            .describe_post_message("ID001", "hello\nworld & more", None)
+            .unwrap();
+        assert!(description.starts_with("POST https://example.com/api/chat.postMessage\n"));
+        assert!(description.contains("authorization: Bearer [REDACTED]"));
+        assert!(!description.contains("test-token"));
+        assert!(description.contains(r#""channel": "ID001""#));
+        assert!(description.contains(r#""text": "hello\nworld & more""#));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn it_post_reply_in_thread() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/chat.postMessage")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("channel".to_string(), "ID001".to_string()),
+                mockito::Matcher::UrlEncoded(
+                    "thread_ts".to_string(),
+                    "1700000000.000100".to_string(),
+                ),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ok": true}"#)
+            .create_async()
+            .await;
+
+        std::env::set_var("RSLACK_TOKEN", "test-token");
+        let config = Config::new(None).unwrap();
+        let slack_client = SlackClient::new(&config, &server.url()).unwrap();
+        slack_client
+            .post_reply("ID001", "done", Some("1700000000.000100"))
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn it_tell_transient_errors() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/api/chat.postMessage")
+            .match_body(mockito::Matcher::UrlEncoded(
+                "channel".to_string(),
+                "ID001".to_string(),
+            ))
+            .with_status(503)
+            .with_body("Service Unavailable")
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/api/chat.postMessage")
+            .match_body(mockito::Matcher::UrlEncoded(
+                "channel".to_string(),
+                "ID002".to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ok": false, "error": "channel_not_found"}"#)
+            .create_async()
+            .await;
+
+        std::env::set_var("RSLACK_TOKEN", "test-token");
+        let config = Config::new(None).unwrap();
+        let slack_client = SlackClient::new(&config, &server.url()).unwrap();
+        let transient = |res: Result<()>| match res {
+            Err(err) => is_transient(&err),
+            Ok(_) => panic!("expected an error"),
+        };
+        assert!(transient(slack_client.post_message("ID001", "hello").await));
+        assert!(!transient(
+            slack_client.post_message("ID002", "hello").await
+        ));
+
+        // Nothing listens on the discard port
+        let offline = SlackClient::new(&config, "http://127.0.0.1:9").unwrap();
+        assert!(transient(offline.post_message("ID001", "hello").await));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn it_post_message_to_channels() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/api/chat.postMessage")
+            .match_body(mockito::Matcher::UrlEncoded(
+                "channel".to_string(),
+                "ID001".to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ok": true}"#)
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/api/chat.postMessage")
+            .match_body(mockito::Matcher::UrlEncoded(
+                "channel".to_string(),
+                "ID002".to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ok": false, "error": "is_archived"}"#)
+            .create_async()
+            .await;
+
+        std::env::set_var("RSLACK_TOKEN", "test-token");
+        let config = Config::new(None).unwrap();
+        let slack_client = SlackClient::new(&config, &server.url()).unwrap();
+        let channels = vec![
+            SlackChannel {
+                id: "ID001".to_string(),
+                name: "general".to_string(),
+            },
+            SlackChannel {
+                id: "ID002".to_string(),
+                name: "archived".to_string(),
+            },
+        ];
+        let results = slack_client
+            .post_message_to_channels(&channels, "hello", None, 2)
+            .await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0.name, "general");
+        assert!(results[0].1.is_ok());
+        assert_eq!(results[1].0.name, "archived");
+        match &results[1].1 {
+            Err(err) => assert_eq!(err.to_string(), "is_archived"),
+            Ok(_) => panic!("expected is_archived error"),
+        }
+    }
+
+    #[test]
+    fn it_parse_channel_ref() {
+        assert_eq!(
+            ChannelRef::parse("general"),
+            ChannelRef::Name("general".to_string())
+        );
+        assert_eq!(
+            ChannelRef::parse("#general"),
+            ChannelRef::Name("general".to_string())
+        );
+        assert_eq!(
+            ChannelRef::parse("C0123456789"),
+            ChannelRef::Id("C0123456789".to_string())
+        );
+        assert_eq!(
+            ChannelRef::parse("https://example.slack.com/archives/C0123456789/p1700000000000000"),
+            ChannelRef::Id("C0123456789".to_string())
+        );
+        assert_eq!(
+            ChannelRef::parse("cafe-team"),
+            ChannelRef::Name("cafe-team".to_string())
+        );
+    }
+
+    #[test]
+    fn it_find_by_channel_ref() {
+        let slack_channels = SlackChannels {
+            channels: vec![SlackChannel {
+                id: "C0123456789".to_string(),
+                name: "general".to_string(),
+            }],
+        };
+        let by_id = slack_channels.find(&ChannelRef::parse("C0123456789"));
+        assert_eq!(by_id.unwrap().name, "general");
+        let by_name = slack_channels.find(&ChannelRef::parse("#general"));
+        assert_eq!(by_name.unwrap().id, "C0123456789");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn it_resolve_unlisted_channel_id() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock(
+                "GET",
+                mockito::Matcher::Regex(r"/api/conversations\.info.*".to_string()),
+            )
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_file("tests/fixtures/slack/conversations_info/ok.json")
+            .create_async()
+            .await;
+
+        std::env::set_var("RSLACK_TOKEN", "test-token");
+        let config = Config::new(None).unwrap();
+        let slack_client = SlackClient::new(&config, &server.url()).unwrap();
+        let channels = SlackChannels::from(vec![]);
+        let resolved = slack_client
+            .resolve_channel(&channels, &ChannelRef::parse("C0987654321"))
+            .await
+            .unwrap();
+        assert_eq!(resolved.unwrap().name, "private-ops");
+
+        let unresolved = slack_client
+            .resolve_channel(&channels, &ChannelRef::parse("general"))
+            .await
+            .unwrap();
+        assert!(unresolved.is_none());
+    }
+
+    #[test]
+    fn it_build_search_query() {
+        let query = SearchQuery {
+            query: "deploy failed".to_string(),
+            in_channel: Some("#ops".to_string()),
+            from: Some("alice".to_string()),
+            after: Some("2026-10-01".to_string()),
+        };
+        assert_eq!(
+            query.to_query_string(),
+            "deploy failed in:#ops from:@alice after:2026-10-01"
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn it_search_messages_across_pages() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/search.messages")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "page".to_string(),
+                "1".to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_file("tests/fixtures/slack/search_messages/page1.json")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/search.messages")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "page".to_string(),
+                "2".to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_file("tests/fixtures/slack/search_messages/page2.json")
+            .create_async()
+            .await;
+
+        std::env::set_var("RSLACK_TOKEN", "test-token");
+        let config = Config::new(None).unwrap();
+        let slack_client = SlackClient::new(&config, &server.url()).unwrap();
+        let query = SearchQuery {
+            query: "deploy".to_string(),
+            ..Default::default()
+        };
+        let matches = slack_client.search_messages(&query, 20).await.unwrap();
+        assert_eq!(matches.len(), 3);
+        assert_eq!(matches[0].channel.name, "ops");
+        assert_eq!(matches[2].username.as_deref(), Some("bob"));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn it_get_history_across_pages() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/conversations.history")
+            .match_query(mockito::Matcher::Regex(
+                r"^channel=C0123456789&oldest=1700000000&limit=200$".to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_file("tests/fixtures/slack/conversations_history/page1.json")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/conversations.history")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "cursor".to_string(),
+                "bmV4dF90czoxNzAwMDAwMTAw".to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_file("tests/fixtures/slack/conversations_history/page2.json")
+            .create_async()
+            .await;
+
+        std::env::set_var("RSLACK_TOKEN", "test-token");
+        let config = Config::new(None).unwrap();
+        let slack_client = SlackClient::new(&config, &server.url()).unwrap();
+        let range = HistoryRange {
+            oldest: Some("1700000000".to_string()),
+            ..Default::default()
+        };
+        let messages = slack_client
+            .get_history("C0123456789", &range)
+            .await
+            .unwrap();
+        assert_eq!(messages.messages.len(), 3);
+        assert!(messages.messages[0].has_replies());
+        assert_eq!(messages.messages[2].text, "first message in range");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn it_get_users() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock(
+                "GET",
+                mockito::Matcher::Regex(r"/api/users\.list.*".to_string()),
+            )
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_file("tests/fixtures/slack/users_list/ok.json")
+            .create_async()
+            .await;
+
+        std::env::set_var("RSLACK_TOKEN", "test-token");
+        let config = Config::new(None).unwrap();
+        let slack_client = SlackClient::new(&config, &server.url()).unwrap();
+        let users = slack_client.get_users().await.unwrap();
+        assert_eq!(users.users.len(), 2);
+        assert_eq!(users.name_of("U0123456789"), "alice");
+        assert_eq!(users.name_of("U0987654321"), "Bob Builder");
+        assert_eq!(users.name_of("U0000000000"), "U0000000000");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn it_get_emoji() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/emoji.list")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_file("tests/fixtures/slack/emoji_list/ok.json")
+            .create_async()
+            .await;
+
+        std::env::set_var("RSLACK_TOKEN", "test-token");
+        let config = Config::new(None).unwrap();
+        let slack_client = SlackClient::new(&config, &server.url()).unwrap();
+        let emoji = slack_client.get_emoji().await.unwrap();
+        assert_eq!(emoji, vec!["partyparrot".to_string(), "shipit".to_string()]);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn it_get_replies() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/conversations.replies")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "ts".to_string(),
+                "1700000300.000300".to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_file("tests/fixtures/slack/conversations_replies/ok.json")
+            .create_async()
+            .await;
+
+        std::env::set_var("RSLACK_TOKEN", "test-token");
+        let config = Config::new(None).unwrap();
+        let slack_client = SlackClient::new(&config, &server.url()).unwrap();
+        let replies = slack_client
+            .get_replies("C0123456789", "1700000300.000300")
+            .await
+            .unwrap();
+        assert_eq!(replies.messages.len(), 3);
+        assert_eq!(replies.messages[2].text, "postmortem doc incoming");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn it_get_messages_follows_cursor_up_to_limit() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/conversations.history")
+            .match_query(mockito::Matcher::Regex(
+                r"^channel=C0123456789&oldest=1700000000&inclusive=true&limit=5$".to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_file("tests/fixtures/slack/conversations_history/page1.json")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/conversations.history")
+            .match_query(mockito::Matcher::Regex(
+                r"&limit=3&cursor=bmV4dF90czoxNzAwMDAwMTAw$".to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_file("tests/fixtures/slack/conversations_history/page2.json")
+            .create_async()
+            .await;
+
+        std::env::set_var("RSLACK_TOKEN", "test-token");
+        let config = Config::new(None).unwrap();
+        let slack_client = SlackClient::new(&config, &server.url()).unwrap();
+        let range = HistoryRange {
+            oldest: Some("1700000000".to_string()),
+            latest: None,
+            inclusive: true,
+        };
+        let messages = slack_client
+            .get_messages("C0123456789", 5, &range)
+            .await
+            .unwrap();
+        assert_eq!(messages.messages.len(), 3);
+    }
 }
@@ -0,0 +1,85 @@
+/// Longest message text accepted by chat.postMessage, in characters
+pub const MAX_MESSAGE_LENGTH: usize = 40_000;
+
+const BROADCASTS: &[&str] = &["channel", "here", "everyone"];
+
+/// `@channel`, `@here` and `@everyone` mentions in a message, typed or encoded as `<!here>`
+pub fn broadcast_mentions(text: &str) -> Vec<&'static str> {
+    BROADCASTS
+        .iter()
+        .copied()
+        .filter(|name| {
+            text.contains(&format!("<!{}>", name))
+                || text
+                    .match_indices(&format!("@{}", name))
+                    .any(|(index, mention)| {
+                        let before = text[..index].chars().next_back();
+                        let after = text[index + mention.len()..].chars().next();
+                        !before.is_some_and(char::is_alphanumeric)
+                            && !after.is_some_and(|c| c.is_alphanumeric() || c == '_')
+                    })
+        })
+        .collect()
+}
+
+/// Split a message into parts of at most `max_len` characters.
+/// Parts end at line breaks where possible, then at spaces.
+pub fn split_message(text: &str, max_len: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+
+    while rest.chars().count() > max_len {
+        let limit = rest
+            .char_indices()
+            .nth(max_len)
+            .map(|(index, _)| index)
+            .unwrap_or(rest.len());
+        let head = &rest[..limit];
+        let split = head
+            .rfind('\n')
+            .or_else(|| head.rfind(' '))
+            .filter(|&index| index > 0)
+            .unwrap_or(limit);
+
+        parts.push(rest[..split].trim_end().to_string());
+        rest = rest[split..].trim_start_matches(['\n', '\r', ' ']);
+    }
+    if !rest.is_empty() || parts.is_empty() {
+        parts.push(rest.to_string());
+    }
+
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broadcast_mentions_typed_and_encoded() {
+        assert_eq!(
+            broadcast_mentions("hey @here and <!channel>"),
+            vec!["channel", "here"]
+        );
+        assert!(broadcast_mentions("mail me@here.com or @heres @channel_ops").is_empty());
+    }
+
+    #[test]
+    fn split_message_at_lines_then_spaces() {
+        assert_eq!(
+            split_message("one two\nthree four five", 12),
+            vec!["one two", "three four", "five"]
+        );
+    }
+
+    #[test]
+    fn split_message_without_spaces() {
+        assert_eq!(split_message("abcdefgh", 3), vec!["abc", "def", "gh"]);
+    }
+
+    #[test]
+    fn split_short_message_is_unchanged() {
+        assert_eq!(split_message("hello", 40), vec!["hello"]);
+        assert_eq!(split_message("", 40), vec![""]);
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+
+/// A message template from the `[templates]` section of the config file,
+/// e.g. `Deploying {{service}} {{version}} to {{env}}`.
+/// `\n` in a template starts a new line.
+pub struct Template {
+    text: String,
+}
+
+impl Template {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.replace("\\n", "\n"),
+        }
+    }
+
+    /// Names of the variables in the order they first appear
+    pub fn variables(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for (_, name) in self.placeholders() {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+        names
+    }
+
+    /// Fill in the variables. Fails if one of them has no value.
+    pub fn render(&self, vars: &BTreeMap<String, String>) -> Result<String> {
+        let missing: Vec<String> = self
+            .variables()
+            .into_iter()
+            .filter(|name| !vars.contains_key(name))
+            .collect();
+        if !missing.is_empty() {
+            anyhow::bail!("Missing template variables: {}", missing.join(", "));
+        }
+
+        let mut rendered = String::new();
+        let mut rest = 0;
+        for (range, name) in self.placeholders() {
+            rendered.push_str(&self.text[rest..range.start]);
+            rendered.push_str(&vars[name]);
+            rest = range.end;
+        }
+        rendered.push_str(&self.text[rest..]);
+        Ok(rendered)
+    }
+
+    /*
+     * Byte range and trimmed name of every `{{name}}` placeholder.
+     * Braces around anything but a plain name are left as they are.
+     */
+    fn placeholders(&self) -> Vec<(std::ops::Range<usize>, &str)> {
+        let mut placeholders = Vec::new();
+        let mut from = 0;
+        while let Some(start) = self.text[from..].find(OPEN).map(|i| from + i) {
+            let Some(end) = self.text[start..].find(CLOSE).map(|i| start + i) else {
+                break;
+            };
+            let name = self.text[start + OPEN.len()..end].trim();
+            if is_name(name) {
+                placeholders.push((start..end + CLOSE.len(), name));
+                from = end + CLOSE.len();
+            } else {
+                from = start + OPEN.len();
+            }
+        }
+        placeholders
+    }
+}
+
+/*
+ * Variable names are letters, digits, '_' and '-'.
+ */
+fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Parse a `KEY=VALUE` template variable given on the command line
+pub fn parse_var(input: &str) -> Result<(String, String), String> {
+    match input.split_once('=') {
+        Some((key, value)) if is_name(key.trim()) => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected KEY=VALUE, got '{}'", input)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn it_lists_variables_once_in_order() {
+        let template = Template::new("{{env}}: Deploying {{ service }} {{version}} to {{env}}");
+        assert_eq!(template.variables(), vec!["env", "service", "version"]);
+    }
+
+    #[test]
+    fn it_renders_variables() {
+        let template = Template::new("Deploying {{service}} {{version}} to {{env}}\\nby {{user}}");
+        let actual = template
+            .render(&vars(&[
+                ("service", "api"),
+                ("version", "v1.2.0"),
+                ("env", "prod"),
+                ("user", "<@U001>"),
+            ]))
+            .unwrap();
+        assert_eq!(actual, "Deploying api v1.2.0 to prod\nby <@U001>");
+    }
+
+    #[test]
+    fn it_leaves_other_braces_alone() {
+        let template = Template::new("{{ not a var }} {} {{x}} {{");
+        assert_eq!(template.variables(), vec!["x"]);
+        assert_eq!(
+            template.render(&vars(&[("x", "1")])).unwrap(),
+            "{{ not a var }} {} 1 {{"
+        );
+    }
+
+    #[test]
+    fn it_fails_on_missing_variables() {
+        let template = Template::new("{{service}} to {{env}}");
+        let err = template.render(&vars(&[("service", "api")])).unwrap_err();
+        assert_eq!(err.to_string(), "Missing template variables: env");
+    }
+
+    #[test]
+    fn it_parses_vars() {
+        assert_eq!(
+            parse_var("version=v1.2=rc1"),
+            Ok(("version".to_string(), "v1.2=rc1".to_string()))
+        );
+        assert_eq!(parse_var("env="), Ok(("env".to_string(), String::new())));
+        assert!(parse_var("service").is_err());
+        assert!(parse_var("=api").is_err());
+    }
+}
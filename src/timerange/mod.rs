@@ -0,0 +1,219 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Days, Duration, Local, NaiveDate, NaiveDateTime, TimeZone};
+
+use crate::slack::HistoryRange;
+
+/*
+ * Parse a point in time given on the command line.
+ * Accepts `2026-10-01`, `2026-10-01 09:30`, `2026-10-01T09:30:00`,
+ * RFC 3339 (`2026-10-01T09:30:00+09:00`), Unix epoch seconds,
+ * `now`, `today`, `yesterday` and durations ago such as `30m`, `2h`, `3d` or `1w`.
+ */
+pub fn parse_time(input: &str) -> Result<DateTime<Local>> {
+    parse_time_at(input, Local::now())
+}
+
+fn parse_time_at(input: &str, now: DateTime<Local>) -> Result<DateTime<Local>> {
+    let input = input.trim();
+
+    match input {
+        "now" => return Ok(now),
+        "today" => return start_of_day(now.date_naive(), input),
+        "yesterday" => {
+            let yesterday = now
+                .date_naive()
+                .checked_sub_days(Days::new(1))
+                .ok_or_else(|| anyhow!("Invalid time: '{}'", input))?;
+            return start_of_day(yesterday, input);
+        }
+        _ => {}
+    }
+
+    if let Some(ago) = parse_duration(input) {
+        return Ok(now - ago);
+    }
+    if let Some(unsigned) = input.strip_prefix(['-', '+']) {
+        if parse_duration(unsigned).is_some() {
+            return Err(anyhow!(
+                "Invalid time: '{}'. Relative times count back from now, so use '{}' without a sign",
+                input,
+                unsigned
+            ));
+        }
+    }
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(input) {
+        return Ok(dt.with_timezone(&Local));
+    }
+
+    for format in [
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+    ] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(input, format) {
+            return local(naive, input);
+        }
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return start_of_day(date, input);
+    }
+
+    if let Ok(epoch) = input.parse::<i64>() {
+        if let chrono::LocalResult::Single(dt) = Local.timestamp_opt(epoch, 0) {
+            return Ok(dt);
+        }
+    }
+
+    Err(anyhow!("Invalid time: '{}'", input))
+}
+
+/*
+ * Parse `30s`, `15m`, `2h`, `3d` or `1w`. The amount is unsigned digits.
+ */
+fn parse_duration(input: &str) -> Option<Duration> {
+    let unit = input.chars().last()?;
+    let amount = &input[..input.len() - unit.len_utf8()];
+    if amount.is_empty() || !amount.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let amount: i64 = amount.parse().ok()?;
+
+    match unit {
+        's' => Duration::try_seconds(amount),
+        'm' => Duration::try_minutes(amount),
+        'h' => Duration::try_hours(amount),
+        'd' => Duration::try_days(amount),
+        'w' => Duration::try_weeks(amount),
+        _ => None,
+    }
+}
+
+fn start_of_day(date: NaiveDate, input: &str) -> Result<DateTime<Local>> {
+    local(date.and_hms_opt(0, 0, 0).unwrap_or_default(), input)
+}
+
+fn local(naive: NaiveDateTime, input: &str) -> Result<DateTime<Local>> {
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| anyhow!("Invalid local time: '{}'", input))
+}
+
+/// Convert a time to a Slack timestamp ("epoch.microseconds")
+pub fn to_slack_ts(time: &DateTime<Local>) -> String {
+    format!("{}.{:06}", time.timestamp(), time.timestamp_subsec_micros())
+}
+
+/// Build a history range from optional `--since` / `--until` values
+pub fn history_range(
+    since: Option<&str>,
+    until: Option<&str>,
+    inclusive: bool,
+) -> Result<HistoryRange> {
+    let oldest = since.map(parse_time).transpose()?;
+    let latest = until.map(parse_time).transpose()?;
+
+    if let (Some(oldest), Some(latest)) = (&oldest, &latest) {
+        if oldest > latest {
+            anyhow::bail!("--since must be earlier than --until");
+        }
+    }
+
+    Ok(HistoryRange {
+        oldest: oldest.as_ref().map(to_slack_ts),
+        latest: latest.as_ref().map(to_slack_ts),
+        inclusive,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+
+    #[test]
+    fn parse_date() {
+        let time = parse_time("2026-10-01").unwrap();
+        assert_eq!(
+            time.format("%Y-%m-%d %H:%M").to_string(),
+            "2026-10-01 00:00"
+        );
+    }
+
+    #[test]
+    fn parse_date_and_time() {
+        let time = parse_time("2026-10-01 09:30").unwrap();
+        assert_eq!((time.hour(), time.minute()), (9, 30));
+    }
+
+    #[test]
+    fn parse_rfc3339_and_epoch() {
+        let rfc3339 = parse_time("2023-11-14T22:13:20Z").unwrap();
+        let epoch = parse_time("1700000000").unwrap();
+        assert_eq!(rfc3339, epoch);
+        assert_eq!(to_slack_ts(&epoch), "1700000000.000000");
+    }
+
+    #[test]
+    fn parse_invalid_time() {
+        assert!(parse_time("someday").is_err());
+    }
+
+    #[test]
+    fn history_range_rejects_reversed_range() {
+        assert!(history_range(Some("2026-10-02"), Some("2026-10-01"), false).is_err());
+        let range = history_range(None, Some("1700000000"), false).unwrap();
+        assert_eq!(range.oldest, None);
+        assert_eq!(range.latest, Some("1700000000.000000".to_string()));
+    }
+
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 10, 19, 15, 45, 0).unwrap()
+    }
+
+    #[test]
+    fn parse_relative_durations() {
+        assert_eq!(
+            parse_time_at("2h", now()).unwrap(),
+            now() - Duration::hours(2)
+        );
+        assert_eq!(
+            parse_time_at("30m", now()).unwrap(),
+            now() - Duration::minutes(30)
+        );
+        assert_eq!(
+            parse_time_at("1w", now()).unwrap(),
+            now() - Duration::weeks(1)
+        );
+        assert!(parse_time_at("2x", now()).is_err());
+    }
+
+    #[test]
+    fn parse_signed_durations_fails() {
+        let err = parse_time_at("-2h", now()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid time: '-2h'. Relative times count back from now, so use '2h' without a sign"
+        );
+        assert!(parse_time_at("+30m", now()).is_err());
+        assert!(parse_time_at("h", now()).is_err());
+    }
+
+    #[test]
+    fn parse_named_days() {
+        let yesterday = parse_time_at("yesterday", now()).unwrap();
+        assert_eq!(
+            yesterday.format("%Y-%m-%d %H:%M").to_string(),
+            "2026-10-18 00:00"
+        );
+        let today = parse_time_at("today", now()).unwrap();
+        assert_eq!(
+            today.format("%Y-%m-%d %H:%M").to_string(),
+            "2026-10-19 00:00"
+        );
+        assert_eq!(parse_time_at("now", now()).unwrap(), now());
+    }
+}
@@ -0,0 +1,175 @@
+use std::path::PathBuf;
+use std::process::{Output, Stdio};
+
+use tokio::process::Command;
+
+use rslack::mock;
+use rslack::slack::FakeSlack;
+
+fn workspace() -> FakeSlack {
+    FakeSlack::new()
+        .with_channel("C001", "general")
+        .with_channel("C002", "random")
+        .with_user("U001", "alice")
+}
+
+/*
+ * Run rslack against a mock workspace without a terminal: stdin is closed and
+ * stdout and stderr are pipes. HOME and the state directory point to an empty
+ * directory so that the user's config, outbox and history are not touched.
+ */
+async fn rslack(name: &str, slack: &FakeSlack, args: &[&str]) -> Output {
+    let url = mock::start(slack.clone()).await.unwrap();
+    let home = home(name);
+    std::fs::remove_dir_all(&home).ok();
+    std::fs::create_dir_all(&home).unwrap();
+
+    let mut command = Command::new(PathBuf::from(env!("CARGO_BIN_EXE_rslack")));
+    for (key, _) in std::env::vars().filter(|(key, _)| key.starts_with("RSLACK_")) {
+        command.env_remove(key);
+    }
+    command
+        .args(args)
+        .env("HOME", &home)
+        .env("XDG_STATE_HOME", home.join("state"))
+        .env("RSLACK_TOKEN", "test-token")
+        .env("RSLACK_BASE_URL", url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .unwrap()
+}
+
+fn home(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rslack-headless-{}-{}", name, std::process::id()))
+}
+
+#[tokio::test]
+async fn dry_run_runs_without_a_terminal() {
+    let slack = workspace();
+    let output = rslack(
+        "dry-run",
+        &slack,
+        &["--dry-run", "-c", "general", "-m", "hi"],
+    )
+    .await;
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("[Dry run] #general (C001)"));
+    assert!(stdout.contains("chat.postMessage"));
+    assert!(slack.messages("C001").is_empty());
+}
+
+#[tokio::test]
+async fn yes_posts_without_a_terminal() {
+    let slack = workspace();
+    let output = rslack(
+        "yes",
+        &slack,
+        &["--yes", "-c", "general,random", "-m", "hi"],
+    )
+    .await;
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("[Success] #general"));
+    assert!(stdout.contains("[Success] #random"));
+    for channel in ["C001", "C002"] {
+        let texts: Vec<String> = slack
+            .messages(channel)
+            .into_iter()
+            .map(|message| message.text)
+            .collect();
+        assert_eq!(texts, ["hi"]);
+    }
+}
+
+#[tokio::test]
+async fn read_runs_without_a_terminal() {
+    let slack = workspace().with_message("C001", "U001", "hello");
+    let output = rslack("read", &slack, &["-r", "-c", "general"]).await;
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8(output.stdout).unwrap().contains("hello"));
+}
+
+#[tokio::test]
+async fn unknown_channels_are_reported_by_name() {
+    let slack = workspace();
+    let output = rslack(
+        "unknown",
+        &slack,
+        &["--yes", "-c", "general,typo", "-c", "C404", "-m", "hi"],
+    )
+    .await;
+
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Channel not found: 'typo', 'C404'\n"
+    );
+    assert!(slack.messages("C001").is_empty());
+}
+
+#[tokio::test]
+async fn mentions_in_messages_are_encoded() {
+    let slack = workspace();
+    let output = rslack(
+        "mentions",
+        &slack,
+        &[
+            "--yes",
+            "-c",
+            "general",
+            "-m",
+            "@alice see #random `@alice`",
+        ],
+    )
+    .await;
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        slack.messages("C001")[0].text,
+        "<@U001> see <#C002> `@alice`"
+    );
+}
+
+#[tokio::test]
+async fn sent_history_keeps_messages_as_typed() {
+    let slack = workspace();
+    let output = rslack(
+        "history",
+        &slack,
+        &["--yes", "-c", "general", "-m", "@alice see #random"],
+    )
+    .await;
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(slack.messages("C001")[0].text, "<@U001> see <#C002>");
+    let history = std::fs::read_to_string(home("history").join("state/rslack/sent.jsonl")).unwrap();
+    assert!(history.contains("\"@alice see #random\""), "{}", history);
+    std::fs::remove_dir_all(home("history")).ok();
+}